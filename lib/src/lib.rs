@@ -111,6 +111,314 @@
 //! struct InnerModel(/* ... */);
 //! ```
 //!
+//! # Field options
+//!
+//! Fields can be annotated to change how they are held by the
+//! generated models:
+//!
+//! ```rust
+//! #[derive(leptos_tea::Model)]
+//! struct Model {
+//!   // Only notifies when the new value is different
+//!   #[model(eq)]
+//!   query: String,
+//!   // Held in a `StoredValue` rather than a signal, which the view
+//!   // model can only read
+//!   #[model(store)]
+//!   abort_handle: Option<u32>,
+//!   // Left out of the view model entirely
+//!   #[model(skip)]
+//!   retries: usize,
+//!   // Private in the update model, but public in the view model
+//!   #[model(update_vis = pub(self))]
+//!   pub token: String,
+//! }
+//! ```
+//!
+//! Snapshots of models with skipped fields use `Default::default()`
+//! for those fields, so restoring a snapshot, such as a persisted
+//! one, leaves them as they are.
+//!
+//! Fields annotated with `#[model(input)]` hold a signal passed in
+//! when initializing the model, such as a component prop, which the
+//! update function can read but not write. With
+//! `#[model(input(on_change = Msg::Variant))]`, the message is also
+//! dispatched with the new value whenever the signal changes:
+//!
+//! ```rust
+//! # use leptos::*;
+//! #[derive(Default)]
+//! enum Msg {
+//!   #[default]
+//!   Init,
+//!   UserIdChanged(u32),
+//! }
+//!
+//! #[derive(leptos_tea::Model)]
+//! struct ProfileModel {
+//!   #[model(input(on_change = Msg::UserIdChanged))]
+//!   user_id: Signal<u32>,
+//!   name: String,
+//! }
+//! ```
+//!
+//! The message type is inferred from the path, and can otherwise be
+//! set with `msg = Type`.
+//!
+//! Models annotated with `#[model(builder)]` can be created with a
+//! generated builder instead, where fields which aren't set take the
+//! value of their `#[model(default = expr)]`, or `Default::default()`:
+//!
+//! ```rust
+//! # use leptos_tea::Cmd;
+//! # #[derive(Default)]
+//! # enum Msg {
+//! #   #[default]
+//! #   Init,
+//! # }
+//! #
+//! # fn update(model: UpdateCounterModel, msg: Msg, _: Cmd<Msg>) {}
+//! #
+//! #[derive(leptos_tea::Model)]
+//! #[model(builder)]
+//! struct CounterModel {
+//!   #[model(default = 1)]
+//!   step: usize,
+//!   counter: usize,
+//! }
+//!
+//! # fn component() {
+//! let (model, msg_dispatcher) =
+//!   CounterModel::builder().counter(5).init(update);
+//! # }
+//! ```
+//!
+//! Fields annotated with `#[model(validate = fn)]` are checked with
+//! `fn(&T) -> Result<(), String>` whenever they change. The model itself
+//! can also be given a validator, which takes the view model. The view
+//! model then has `.errors()`, with a memo holding the error of each
+//! field, `.error()` for the model-level validator, and `.is_valid()`:
+//!
+//! ```rust
+//! # use leptos::*;
+//! fn required(value: &str) -> Result<(), String> {
+//!   if value.is_empty() {
+//!     Err("required".to_string())
+//!   } else {
+//!     Ok(())
+//!   }
+//! }
+//!
+//! fn passwords_match(model: &ViewSignUpModel) -> Result<(), String> {
+//!   if model.password.get() == model.confirm.get() {
+//!     Ok(())
+//!   } else {
+//!     Err("passwords don't match".to_string())
+//!   }
+//! }
+//!
+//! #[derive(leptos_tea::Model)]
+//! #[model(validate = passwords_match)]
+//! struct SignUpModel {
+//!   #[model(validate = required)]
+//!   email: String,
+//!   password: String,
+//!   confirm: String,
+//! }
+//! ```
+//!
+//! The memos are created once, when the model is initialized, so they
+//! can be read from anywhere the view model is.
+//!
+//! Models annotated with `#[model(form)]` also get a `{Name}FieldMsg`
+//! enum with a variant setting each field, which the update function
+//! handles with `.apply_field_msg()`. The view model has a `bind_*`
+//! method per field, returning its value and a setter dispatching the
+//! matching variant. Fields which are less visible than the model
+//! don't get a variant:
+//!
+//! ```rust
+//! # use leptos::*;
+//! # use leptos_tea::Cmd;
+//! #[derive(Default, leptos_tea::Model)]
+//! #[model(form)]
+//! struct SignUpModel {
+//!   email: String,
+//!   age: u8,
+//! }
+//!
+//! #[derive(Default)]
+//! enum Msg {
+//!   #[default]
+//!   Init,
+//!   Field(SignUpModelFieldMsg),
+//! }
+//!
+//! impl From<SignUpModelFieldMsg> for Msg {
+//!   fn from(msg: SignUpModelFieldMsg) -> Self {
+//!     Msg::Field(msg)
+//!   }
+//! }
+//!
+//! fn update(model: UpdateSignUpModel, msg: Msg, _: Cmd<Msg>) {
+//!   match msg {
+//!     Msg::Field(msg) => model.apply_field_msg(msg),
+//!     Msg::Init => {}
+//!   }
+//! }
+//!
+//! #[component]
+//! fn SignUp() -> impl IntoView {
+//!   let (model, msg_dispatcher) = SignUpModel::default().init(update);
+//!
+//!   let (email, set_email) = model.bind_email(msg_dispatcher);
+//!   let (age, set_age) = model.bind_age(msg_dispatcher);
+//!
+//!   view! {
+//!     <input
+//!       prop:value=email
+//!       on:input=move |ev| set_email(event_target_value(&ev))
+//!     />
+//!     <input
+//!       prop:value=age
+//!       on:input=move |ev| {
+//!         if let Ok(age) = event_target_value(&ev).parse() {
+//!           set_age(age);
+//!         }
+//!       }
+//!     />
+//!   }
+//! }
+//! ```
+//!
+//! With the `leptos` feature, `leptos_tea::on_input(set_age)` returns
+//! the same handler as the one for `age`.
+//!
+//! The generated models are named `Update{Name}` and `View{Name}`
+//! unless set with `#[model(update = "...", view = "...")]`.
+//!
+//! Derives and attributes can be forwarded to the generated update
+//! and view models with `#[model(derive(...))]` and
+//! `#[model(attr(...))]`. Deriving `Debug` this way prints the current
+//! value of every field, rather than the ids of their signals.
+//!
+//! Crates which re-export `leptos_tea` can set the path the generated
+//! code uses to reach it with `#[model(crate = path::to::leptos_tea)]`.
+//!
+//! # Sharing models
+//!
+//! Calling `.provide()` instead of `.init()` also provides the view
+//! model and message dispatcher to child components, which can then
+//! fetch them with `Model::use_view()` and `Model::use_dispatcher()`.
+//!
+//! ```rust
+//! # use leptos::*;
+//! # use leptos_tea::Cmd;
+//! #
+//! # #[derive(Default, leptos_tea::Model)]
+//! # struct CounterModel {
+//! #   counter: usize,
+//! # }
+//! #
+//! # #[derive(Default)]
+//! # enum Msg {
+//! #   #[default]
+//! #   Init,
+//! #   Increment,
+//! # }
+//! #
+//! # fn update(model: UpdateCounterModel, msg: Msg, _: Cmd<Msg>) {}
+//! #
+//! #[component]
+//! fn App() -> impl IntoView {
+//!   CounterModel::default().provide(update);
+//!
+//!   view! { <Counter /> }
+//! }
+//!
+//! #[component]
+//! fn Counter() -> impl IntoView {
+//!   let model = CounterModel::use_view();
+//!   let msg_dispatcher = CounterModel::use_dispatcher::<Msg>();
+//!
+//!   view! {
+//!     <h1>{model.counter}</h1>
+//!     <button on:click=msg_dispatcher.on(|_| Msg::Increment)>"+"</button>
+//!   }
+//! }
+//! ```
+//!
+//! Models which should be shared by the whole app can instead be
+//! registered as global stores with `.init_global()`. See the
+//! [`store`] module.
+//!
+//! Signals owned by someone else, such as a parent component, can be
+//! adopted by the model with `Model::from_signals()`, which takes a
+//! signal per field and returns the update model. Initializing it
+//! binds the model to those signals both ways:
+//!
+//! ```rust
+//! # use leptos::*;
+//! # use leptos_tea::Cmd;
+//! #
+//! # #[derive(Default, leptos_tea::Model)]
+//! # struct CounterModel {
+//! #   counter: usize,
+//! # }
+//! #
+//! # #[derive(Default)]
+//! # enum Msg {
+//! #   #[default]
+//! #   Init,
+//! # }
+//! #
+//! # fn update(model: UpdateCounterModel, msg: Msg, _: Cmd<Msg>) {}
+//! #
+//! #[component]
+//! fn Counter(counter: RwSignal<usize>) -> impl IntoView {
+//!   let (model, _) = CounterModel::from_signals(counter).init(update);
+//!
+//!   view! { <h1>{model.counter}</h1> }
+//! }
+//! ```
+//!
+//! # Messages
+//!
+//! Message enums can derive `leptos_tea::Msg`, which generates a
+//! `Dispatch{Name}` trait implemented for their `MsgDispatcher`, with a
+//! method dispatching each variant. The variant annotated with
+//! `#[msg(init)]` is used as the `Default`, and `.variant_name()`
+//! returns the name of the variant, such as for tracing:
+//!
+//! ```rust
+//! # use leptos::*;
+//! # use leptos_tea::MsgDispatcher;
+//! # #[derive(Clone)]
+//! # enum Filter {
+//! #   All,
+//! #   Done,
+//! # }
+//! #
+//! #[derive(leptos_tea::Msg)]
+//! enum Msg {
+//!   #[msg(init)]
+//!   Init,
+//!   SetFilter(Filter),
+//!   Rename { id: u32, name: String },
+//! }
+//!
+//! fn view(msg_dispatcher: MsgDispatcher<Msg>) -> impl IntoView {
+//!   view! {
+//!     <button on:click=move |_| msg_dispatcher.set_filter(Filter::Done)>
+//!       "Done"
+//!     </button>
+//!   }
+//! }
+//! ```
+//!
+//! Methods which collide with those of `MsgDispatcher`, such as
+//! `dispatch`, have to be called through the trait.
+//!
 //! # Limitations
 //!
 //! `leptos_tea::Model` currently only supports tuple and field structs.
@@ -118,43 +426,271 @@
 //!
 //! # Features
 //!
-//! - `nightly`: Implements `Fn(Msg)` for [`MsgDispatcher`].
+//! - `nightly`: Implements `Fn(Msg)` for [`MsgDispatcher`]. On stable,
+//!   [`MsgDispatcher::into_fn`] and [`MsgDispatcher::on`] provide the
+//!   same ergonomics.
+//! - `leptos`: Allows converting a [`MsgDispatcher`] into a
+//!   `leptos::Callback`, and provides `on_input` for binding form
+//!   inputs.
+//! - `persist`: Allows persisting models with
+//!   `#[model(persist(key = "..."))]`. See the `persist` module.
+//! - `ssr` and `hydrate`: Transfers models annotated with
+//!   `#[model(ssr)]` from the server to the client. See the [`ssr`]
+//!   module.
 
 #[doc(hidden)]
 pub use futures;
-use futures::{channel::mpsc::UnboundedSender, FutureExt, SinkExt};
+use futures::{
+  channel::{mpsc::UnboundedSender, oneshot},
+  FutureExt, Sink, SinkExt, Stream,
+};
 #[doc(hidden)]
 pub use leptos_reactive;
 use leptos_reactive::*;
 pub use leptos_tea_macros::*;
+#[doc(hidden)]
+pub use program::init;
+pub use program::ProgramHandle;
+pub use signal::EqSignal;
+pub use signal::ReadStoredValue;
+#[doc(hidden)]
+pub use signal::{debug_signal, debug_stored_value};
 use smallvec::SmallVec;
-use std::{future::Future, pin::Pin};
+use std::{
+  fmt,
+  future::Future,
+  pin::Pin,
+  rc::Rc,
+  task::{Context, Poll},
+};
+
+#[cfg(feature = "persist")]
+pub mod persist;
+mod program;
+mod signal;
+pub mod ssr;
+pub mod store;
 
 type CmdFut<Msg> = Pin<Box<dyn Future<Output = SmallVec<[Msg; 4]>>>>;
 
+/// Shared by every message and command descending from a
+/// [`MsgDispatcher::dispatch_and_settle`] call. The waiting future
+/// resolves once the last clone is dropped.
+type Settled = Rc<oneshot::Sender<()>>;
+
+/// What, if anything, is waiting on a message to be handled.
+enum Waiter {
+  /// Resolved as soon as the update function returns.
+  Processed(oneshot::Sender<()>),
+  /// Resolved once the message and everything it caused settles.
+  Settled(Settled),
+}
+
+impl Waiter {
+  fn settled(&self) -> Option<Settled> {
+    match self {
+      Self::Processed(_) => None,
+      Self::Settled(settled) => Some(settled.clone()),
+    }
+  }
+
+  /// Called once the update function is done with the message.
+  fn processed(self) {
+    if let Self::Processed(tx) = self {
+      let _ = tx.send(());
+    }
+  }
+}
+
+/// A message, along with whoever is waiting for it to be handled.
+struct Envelope<Msg> {
+  msg: Msg,
+  waiter: Option<Waiter>,
+}
+
+impl<Msg> Envelope<Msg> {
+  fn new(msg: Msg) -> Self {
+    Self { msg, waiter: None }
+  }
+
+  fn settled(msg: Msg, settled: Option<Settled>) -> Self {
+    Self {
+      msg,
+      waiter: settled.map(Waiter::Settled),
+    }
+  }
+}
+
+/// Configures how a model runs, passed to the `init_with` method
+/// generated by the [`Model`] derive macro.
+///
+/// ```rust
+/// # use leptos_tea::{Cmd, Options};
+/// #[derive(Default, leptos_tea::Model)]
+/// struct DraftModel {
+///   text: String,
+/// }
+///
+/// #[derive(Default)]
+/// enum Msg {
+///   #[default]
+///   Init,
+///   Dispose,
+/// }
+///
+/// fn update(model: UpdateDraftModel, msg: Msg, _: Cmd<Msg>) {
+///   match msg {
+///     Msg::Init => {}
+///     Msg::Dispose => { /* save the draft */ }
+///   }
+/// }
+///
+/// # fn component() {
+/// let (model, msg_dispatcher) = DraftModel::default()
+///   .init_with(update, Options::default().on_dispose(Msg::Dispose));
+/// # }
+/// ```
+pub struct Options<Msg> {
+  on_dispose: Option<Msg>,
+  batching: Batching,
+}
+
+impl<Msg> Default for Options<Msg> {
+  fn default() -> Self {
+    Self {
+      on_dispose: None,
+      batching: Batching::default(),
+    }
+  }
+}
+
+impl<Msg> Options<Msg> {
+  /// Sends `msg` to the update function when the [`Owner`] the
+  /// model was initialized in is cleaned up.
+  ///
+  /// The message is handled synchronously while the owner is being
+  /// disposed, so messages and commands issued by the update
+  /// function in response will never run.
+  pub fn on_dispose(mut self, msg: Msg) -> Self {
+    self.on_dispose = Some(msg);
+
+    self
+  }
+
+  /// Sets how signal writes made by the update function are batched.
+  /// Defaults to [`Batching::PerMessage`].
+  pub fn batching(mut self, batching: Batching) -> Self {
+    self.batching = batching;
+
+    self
+  }
+}
+
+/// How signal writes made by the update function are batched
+/// together, set with [`Options::batching`].
+///
+/// While batched, effects and views observing the model only run
+/// once the batch ends, rather than after every write, so they never
+/// see a half-updated model.
+///
+/// ```rust
+/// # use leptos::*;
+/// # use leptos_tea::Cmd;
+/// # use std::{cell::Cell, rc::Rc};
+/// #[derive(Default, leptos_tea::Model)]
+/// struct RangeModel {
+///   start: usize,
+///   end: usize,
+/// }
+///
+/// #[derive(Default)]
+/// enum Msg {
+///   #[default]
+///   Init,
+///   Shift(usize),
+/// }
+///
+/// fn update(model: UpdateRangeModel, msg: Msg, _: Cmd<Msg>) {
+///   if let Msg::Shift(by) = msg {
+///     model.start.update(|start| *start += by);
+///     model.end.update(|end| *end += by);
+///   }
+/// }
+///
+/// # async fn component() {
+/// let (model, msg_dispatcher) = RangeModel::default().init(update);
+///
+/// let runs = Rc::new(Cell::new(0));
+///
+/// create_effect({
+///   let runs = runs.clone();
+///
+///   move |_| {
+///     model.start.track();
+///     model.end.track();
+///
+///     runs.set(runs.get() + 1);
+///   }
+/// });
+///
+/// msg_dispatcher.dispatch_and_wait(Msg::Shift(1)).await;
+/// msg_dispatcher.dispatch_and_wait(Msg::Shift(2)).await;
+///
+/// // Once initially, then once per message
+/// assert_eq!(runs.get(), 3);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Batching {
+  /// Every write notifies observers immediately.
+  None,
+  /// Writes are batched for each call to the update function.
+  #[default]
+  PerMessage,
+  /// Writes are batched for every message that is handled at once,
+  /// such as messages queued while the model was paused.
+  PerFlush,
+}
+
 /// Command manager that allows dispatching messages and running
 /// asynchronous operations.
 pub struct Cmd<Msg: 'static> {
-  msg_dispatcher: StoredValue<UnboundedSender<Msg>>,
+  /// `None` when messages and commands should be discarded.
+  msg_dispatcher: Option<StoredValue<UnboundedSender<Envelope<Msg>>>>,
   msgs: SmallVec<[Msg; 4]>,
   cmds: SmallVec<[CmdFut<Msg>; 4]>,
   owner: Owner,
+  settled: Option<Settled>,
 }
 
 impl<Msg: 'static> Cmd<Msg> {
-  #[doc(hidden)]
-  ///
-  /// You shouldn't need to use this, as it will be
-  /// code generated by the [`Model`] derive macro.
-  pub fn new(
+  fn new(
     owner: Owner,
-    msg_dispatcher: StoredValue<UnboundedSender<Msg>>,
+    msg_dispatcher: StoredValue<UnboundedSender<Envelope<Msg>>>,
+    settled: Option<Settled>,
   ) -> Self {
     Self {
-      msg_dispatcher,
+      msg_dispatcher: Some(msg_dispatcher),
+      cmds: Default::default(),
+      msgs: Default::default(),
+      owner,
+      settled,
+    }
+  }
+
+  /// Creates a [`Cmd`] which discards all messages and commands,
+  /// used when replaying messages which were already handled.
+  #[cfg_attr(
+    not(all(feature = "hydrate", not(feature = "ssr"))),
+    allow(dead_code)
+  )]
+  fn discarding(owner: Owner) -> Self {
+    Self {
+      msg_dispatcher: None,
       cmds: Default::default(),
       msgs: Default::default(),
       owner,
+      settled: None,
     }
   }
 
@@ -181,6 +717,30 @@ impl<Msg: 'static> Cmd<Msg> {
       .push(Box::pin(cmd.map(|i| i.into_iter().collect())));
   }
 
+  /// Same as [`Cmd::cmd`], but the task, along with any messages it
+  /// results in, isn't waited on by
+  /// [`MsgDispatcher::dispatch_and_settle`], nor by the server before
+  /// sending `#[model(ssr)]` models to the client.
+  ///
+  /// Meant for tasks which run for as long as the model does, such
+  /// as listening to a websocket, which would otherwise never settle.
+  pub fn detached_cmd<Fut, I>(&mut self, cmd: Fut)
+  where
+    Fut: Future<Output = I> + 'static,
+    I: IntoIterator<Item = Msg>,
+  {
+    // Performed as soon as it drops, same as this one will be
+    let mut detached = Self {
+      msg_dispatcher: self.msg_dispatcher,
+      msgs: Default::default(),
+      cmds: Default::default(),
+      owner: self.owner,
+      settled: None,
+    };
+
+    detached.cmd(cmd);
+  }
+
   /// Manually perform all commands and dispatch messages now rather
   /// than when dropping.
   pub fn perform(&mut self) {
@@ -191,6 +751,7 @@ impl<Msg: 'static> Cmd<Msg> {
       msgs: core::mem::take(&mut self.msgs),
       cmds: core::mem::take(&mut self.cmds),
       owner: self.owner,
+      settled: self.settled.clone(),
     };
   }
 }
@@ -204,6 +765,7 @@ impl<Msg: 'static> Clone for Cmd<Msg> {
       msgs: Default::default(),
       cmds: Default::default(),
       owner: self.owner,
+      settled: self.settled.clone(),
     }
   }
 }
@@ -214,22 +776,30 @@ impl<Msg: 'static> Drop for Cmd<Msg> {
   fn drop(&mut self) {
     let owner = self.owner;
 
-    if let Some(msg_dispatcher) = self.msg_dispatcher.try_get_value() {
+    if let Some(msg_dispatcher) = self
+      .msg_dispatcher
+      .and_then(|msg_dispatcher| msg_dispatcher.try_get_value())
+    {
       for cmd in std::mem::take(&mut self.cmds) {
         let mut msg_dispatcher = msg_dispatcher.clone();
+        let settled = self.settled.clone();
 
         spawn_local_with_owner(owner, async move {
           let mut cmd = cmd.await.into_iter();
 
           if let Some(msg) = cmd.next() {
-            msg_dispatcher.send(msg).await.unwrap();
+            let _ = msg_dispatcher
+              .send(Envelope::settled(msg, settled.clone()))
+              .await;
           }
 
           for msg in cmd {
             let mut msg_dispatcher = msg_dispatcher.clone();
+            let settled = settled.clone();
 
             spawn_local_with_owner(owner, async move {
-              msg_dispatcher.send(msg).await.unwrap()
+              let _ =
+                msg_dispatcher.send(Envelope::settled(msg, settled)).await;
             });
           }
         });
@@ -237,9 +807,10 @@ impl<Msg: 'static> Drop for Cmd<Msg> {
 
       for msg in std::mem::take(&mut self.msgs) {
         let mut msg_dispatcher = msg_dispatcher.clone();
+        let settled = self.settled.clone();
 
         spawn_local_with_owner(owner, async move {
-          msg_dispatcher.send(msg).await.unwrap();
+          let _ = msg_dispatcher.send(Envelope::settled(msg, settled)).await;
         });
       }
     }
@@ -247,7 +818,10 @@ impl<Msg: 'static> Drop for Cmd<Msg> {
 }
 
 /// Used to send messages to the `update` function.
-pub struct MsgDispatcher<Msg: 'static>(StoredValue<UnboundedSender<Msg>>);
+pub struct MsgDispatcher<Msg: 'static> {
+  tx: StoredValue<UnboundedSender<Envelope<Msg>>>,
+  remote_tx: StoredValue<UnboundedSender<Msg>>,
+}
 
 impl<Msg: 'static> Clone for MsgDispatcher<Msg> {
   fn clone(&self) -> Self {
@@ -280,12 +854,113 @@ impl<Msg> Fn<(Msg,)> for MsgDispatcher<Msg> {
   }
 }
 
-impl<Msg> MsgDispatcher<Msg> {
-  #[doc(hidden)]
-  pub fn new(msg_dispatcher: StoredValue<UnboundedSender<Msg>>) -> Self {
-    Self(msg_dispatcher)
+/// Allows a [`MsgDispatcher`] to be used wherever a
+/// [`WriteSignal`] is expected, with `msg_dispatcher.set(msg)`
+/// being the same as [`MsgDispatcher::dispatch`].
+impl<Msg> SignalSet<Msg> for MsgDispatcher<Msg> {
+  fn set(&self, msg: Msg) {
+    self.dispatch(msg);
+  }
+
+  fn try_set(&self, msg: Msg) -> Option<Msg> {
+    if self.is_disposed() {
+      Some(msg)
+    } else {
+      self.dispatch(msg);
+
+      None
+    }
+  }
+}
+
+/// Allows forwarding streams of messages to the update function.
+///
+/// ```rust
+/// # use futures::{Stream, StreamExt};
+/// # use leptos_tea::MsgDispatcher;
+/// # enum Msg {}
+/// async fn forward(
+///   msgs: impl Stream<Item = Msg>,
+///   msg_dispatcher: MsgDispatcher<Msg>,
+/// ) {
+///   // Stops once the stream ends or the model is disposed
+///   let _ = msgs.map(Ok).forward(msg_dispatcher).await;
+/// }
+/// ```
+///
+/// Messages are sent immediately, the same as
+/// [`MsgDispatcher::dispatch_immediate`].
+impl<Msg> Sink<Msg> for MsgDispatcher<Msg> {
+  type Error = Disposed;
+
+  fn poll_ready(
+    self: Pin<&mut Self>,
+    _: &mut Context<'_>,
+  ) -> Poll<Result<(), Self::Error>> {
+    if self.is_disposed() {
+      Poll::Ready(Err(Disposed))
+    } else {
+      Poll::Ready(Ok(()))
+    }
   }
 
+  fn start_send(self: Pin<&mut Self>, msg: Msg) -> Result<(), Self::Error> {
+    self
+      .tx
+      .try_get_value()
+      .ok_or(Disposed)?
+      .unbounded_send(Envelope::new(msg))
+      .map_err(|_| Disposed)
+  }
+
+  fn poll_flush(
+    self: Pin<&mut Self>,
+    _: &mut Context<'_>,
+  ) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn poll_close(
+    self: Pin<&mut Self>,
+    _: &mut Context<'_>,
+  ) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+}
+
+impl<Msg> From<MsgDispatcher<Msg>> for SignalSetter<Msg> {
+  fn from(msg_dispatcher: MsgDispatcher<Msg>) -> Self {
+    SignalSetter::map(move |msg| msg_dispatcher.dispatch(msg))
+  }
+}
+
+#[cfg(feature = "leptos")]
+impl<Msg> From<MsgDispatcher<Msg>> for leptos::Callback<Msg> {
+  fn from(msg_dispatcher: MsgDispatcher<Msg>) -> Self {
+    leptos::Callback::new(move |msg| msg_dispatcher.dispatch(msg))
+  }
+}
+
+/// Returns an `input` event handler which parses the value of the
+/// event target and passes it to `set`. Values which fail to parse
+/// are ignored.
+///
+/// Meant for the setters returned by the `bind_*` methods generated
+/// for models annotated with `#[model(form)]`.
+#[cfg(feature = "leptos")]
+pub fn on_input<T, E>(set: impl Fn(T) + 'static) -> impl Fn(E) + 'static
+where
+  T: std::str::FromStr,
+  E: leptos::wasm_bindgen::JsCast,
+{
+  move |ev| {
+    if let Ok(value) = leptos::event_target_value(&ev).parse() {
+      set(value);
+    }
+  }
+}
+
+impl<Msg> MsgDispatcher<Msg> {
   /// Dispatches the message to the update function.
   ///
   /// Does not immediately send the value, rather it waits for
@@ -296,21 +971,88 @@ impl<Msg> MsgDispatcher<Msg> {
   /// This is the same as calling  `msg_dispatcher(msg)`
   /// on nightly.
   pub fn dispatch(self, msg: Msg) {
-    if let Some(mut msg_dispatcher) = self.0.try_get_value() {
-      spawn_local(async move {
-        msg_dispatcher.send(msg).await.unwrap();
-      });
-    }
+    self.send(Envelope::new(msg));
   }
 
   /// Dispatches the message immediately, rather than waiting for
   /// the next micro-task.
   pub fn dispatch_immediate(self, msg: Msg) {
-    if let Some(msg_dispatcher) = self.0.try_get_value() {
-      msg_dispatcher.unbounded_send(msg).unwrap();
+    if let Some(msg_dispatcher) = self.tx.try_get_value() {
+      let _ = msg_dispatcher.unbounded_send(Envelope::new(msg));
+    }
+  }
+
+  /// Dispatches the message, returning a future which resolves
+  /// once the update function has handled it.
+  ///
+  /// Commands spawned while handling the message are not waited
+  /// on. If you need that, refer to
+  /// [`MsgDispatcher::dispatch_and_settle`].
+  ///
+  /// The future also resolves if the model is disposed before
+  /// the message could be handled.
+  pub fn dispatch_and_wait(self, msg: Msg) -> impl Future<Output = ()> {
+    let (tx, rx) = oneshot::channel();
+
+    self.send(Envelope {
+      msg,
+      waiter: Some(Waiter::Processed(tx)),
+    });
+
+    async move {
+      let _ = rx.await;
     }
   }
 
+  /// Same as [`MsgDispatcher::dispatch_and_wait`], but additionally
+  /// waits for every command spawned while handling the message to
+  /// finish, along with any messages those commands produce.
+  ///
+  /// Commands added with [`Cmd::detached_cmd`] are not waited on.
+  pub fn dispatch_and_settle(self, msg: Msg) -> impl Future<Output = ()> {
+    let (tx, rx) = oneshot::channel();
+
+    self.send(Envelope {
+      msg,
+      waiter: Some(Waiter::Settled(Rc::new(tx))),
+    });
+
+    async move {
+      let _ = rx.await;
+    }
+  }
+
+  /// Sends a request to the update function and waits for it to
+  /// be answered through the provided [`Reply`].
+  ///
+  /// ```rust
+  /// # use leptos_tea::{MsgDispatcher, Reply};
+  /// enum Msg {
+  ///   TotalPrice(Reply<f64>),
+  /// }
+  ///
+  /// async fn total_price(msg_dispatcher: MsgDispatcher<Msg>) -> f64 {
+  ///   msg_dispatcher
+  ///     .ask(Msg::TotalPrice)
+  ///     .await
+  ///     .unwrap_or_default()
+  /// }
+  /// ```
+  ///
+  /// Resolves to [`Disposed`] if the model is disposed before
+  /// answering, or if the update function drops the [`Reply`]
+  /// without sending anything.
+  pub fn ask<T>(
+    self,
+    msg: impl FnOnce(Reply<T>) -> Msg,
+  ) -> impl Future<Output = Result<T, Disposed>> {
+    let (tx, rx) = oneshot::channel();
+
+    self.dispatch(msg(Reply(tx)));
+
+    async move { rx.await.map_err(|_| Disposed) }
+  }
+
   /// Batches multiple messages together.
   ///
   /// All messages are sent one after another.
@@ -322,4 +1064,230 @@ impl<Msg> MsgDispatcher<Msg> {
       self.dispatch(msg);
     }
   }
+
+  /// Returns `true` if the model was disposed or stopped, meaning
+  /// messages will no longer be handled.
+  pub fn is_disposed(self) -> bool {
+    self
+      .tx
+      .try_with_value(|msg_dispatcher| msg_dispatcher.is_closed())
+      .unwrap_or(true)
+  }
+
+  /// Returns a dispatcher which can be sent to other threads, or
+  /// used from outside the reactive runtime, such as from within
+  /// a JS callback.
+  ///
+  /// ```rust
+  /// # use leptos_tea::MsgDispatcher;
+  /// enum Msg {
+  ///   Progress(u8),
+  /// }
+  ///
+  /// fn spawn_work(msg_dispatcher: MsgDispatcher<Msg>) {
+  ///   let remote = msg_dispatcher.remote();
+  ///
+  ///   std::thread::spawn(move || {
+  ///     for progress in 0..=100 {
+  ///       if remote.dispatch(Msg::Progress(progress)).is_err() {
+  ///         break;
+  ///       }
+  ///     }
+  ///   });
+  /// }
+  /// ```
+  pub fn remote(self) -> RemoteDispatcher<Msg> {
+    let tx = self.remote_tx.try_get_value().unwrap_or_else(|| {
+      // Already disposed, so hand out a closed channel
+      let (tx, _) = futures::channel::mpsc::unbounded();
+
+      tx
+    });
+
+    RemoteDispatcher(tx)
+  }
+
+  /// Returns a closure which dispatches the message it's called with.
+  ///
+  /// This is the stable equivalent of calling `msg_dispatcher(msg)`
+  /// on nightly.
+  pub fn into_fn(self) -> impl Fn(Msg) + Copy {
+    move |msg| self.dispatch(msg)
+  }
+
+  /// Returns an event handler which maps the event to a message
+  /// and dispatches it.
+  ///
+  /// ```rust
+  /// # use leptos::*;
+  /// # use leptos_tea::MsgDispatcher;
+  /// enum Msg {
+  ///   Increment,
+  ///   SetName(String),
+  /// }
+  ///
+  /// fn view(msg_dispatcher: MsgDispatcher<Msg>) -> impl IntoView {
+  ///   view! {
+  ///     <button on:click=msg_dispatcher.on(|_| Msg::Increment)>"+"</button>
+  ///     <input on:input=msg_dispatcher.on(|ev| {
+  ///       Msg::SetName(event_target_value(&ev))
+  ///     }) />
+  ///   }
+  /// }
+  /// ```
+  pub fn on<E>(self, msg: impl Fn(E) -> Msg + 'static) -> impl Fn(E) + 'static {
+    move |ev| self.dispatch(msg(ev))
+  }
+
+  /// Converts this dispatcher into a [`SignalSetter`], for APIs
+  /// which expect a setter rather than a dispatcher.
+  pub fn into_signal_setter(self) -> SignalSetter<Msg> {
+    self.into()
+  }
+
+  /// Converts this dispatcher into a [`leptos::Callback`].
+  #[cfg(feature = "leptos")]
+  pub fn into_callback(self) -> leptos::Callback<Msg> {
+    self.into()
+  }
+
+  fn send(self, envelope: Envelope<Msg>) {
+    if let Some(mut msg_dispatcher) = self.tx.try_get_value() {
+      spawn_local(async move {
+        let _ = msg_dispatcher.send(envelope).await;
+      });
+    }
+  }
+}
+
+/// Sends messages to the update function from other threads, or
+/// from outside the reactive runtime.
+///
+/// Created with [`MsgDispatcher::remote`]. Unlike [`MsgDispatcher`],
+/// this is [`Send`] and [`Sync`] as long as `Msg` is [`Send`].
+pub struct RemoteDispatcher<Msg>(UnboundedSender<Msg>);
+
+impl<Msg> Clone for RemoteDispatcher<Msg> {
+  fn clone(&self) -> Self {
+    Self(self.0.clone())
+  }
+}
+
+impl<Msg> fmt::Debug for RemoteDispatcher<Msg> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("RemoteDispatcher")
+      .field("is_disposed", &self.is_disposed())
+      .finish()
+  }
+}
+
+impl<Msg> RemoteDispatcher<Msg> {
+  /// Dispatches the message to the update function.
+  ///
+  /// Returns [`Disposed`] if the model was disposed or stopped,
+  /// in which case the message is dropped.
+  pub fn dispatch(&self, msg: Msg) -> Result<(), Disposed> {
+    self.0.unbounded_send(msg).map_err(|_| Disposed)
+  }
+
+  /// Returns `true` if the model was disposed or stopped, meaning
+  /// messages will no longer be handled.
+  pub fn is_disposed(&self) -> bool {
+    self.0.is_closed()
+  }
+}
+
+/// Implemented by the view models generated by the [`Model`] derive
+/// macro, allowing the whole model to be read at once.
+///
+/// Only usable when every field of the model is [`Clone`].
+pub trait Snapshot {
+  /// The model this is a view of.
+  type Model;
+
+  /// Returns the current state of the model.
+  ///
+  /// Every field is tracked when called in a reactive context.
+  fn snapshot(&self) -> Self::Model;
+}
+
+#[doc(hidden)]
+/// Implemented for every model by the [`Model`] derive macro, so the
+/// generated types of nested models can be named.
+pub trait SplitModel {
+  /// The update model passed to the update function.
+  type Update: Copy + 'static;
+  /// The view model returned when initializing the model.
+  type View: Copy + 'static;
+}
+
+#[doc(hidden)]
+/// Implemented only for `T` itself, allowing generic code to require
+/// that a type parameter is some concrete type.
+pub trait SameAs<T> {
+  /// Converts `value` into `Self`, which is `T`.
+  fn from_same(value: T) -> Self;
+}
+
+impl<T> SameAs<T> for T {
+  fn from_same(value: T) -> Self {
+    value
+  }
 }
+
+#[doc(hidden)]
+/// Streams a snapshot of the view model every time it changes.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`] derive macro.
+pub fn changes<V>(view_model: V) -> impl Stream<Item = V::Model>
+where
+  V: Snapshot + 'static,
+{
+  let (tx, rx) = futures::channel::mpsc::unbounded();
+
+  create_effect(move |_| {
+    let _ = tx.unbounded_send(view_model.snapshot());
+  });
+
+  rx
+}
+
+/// Used by the update function to answer a request sent
+/// with [`MsgDispatcher::ask`].
+pub struct Reply<T>(oneshot::Sender<T>);
+
+impl<T> Reply<T> {
+  /// Answers the request.
+  ///
+  /// The value is dropped if the asker is no longer waiting.
+  pub fn send(self, value: T) {
+    let _ = self.0.send(value);
+  }
+
+  /// Returns `true` if the asker is no longer waiting for an answer.
+  pub fn is_canceled(&self) -> bool {
+    self.0.is_canceled()
+  }
+}
+
+impl<T> fmt::Debug for Reply<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Reply")
+      .field("is_canceled", &self.is_canceled())
+      .finish()
+  }
+}
+
+/// Error returned when the model was disposed before it could
+/// handle a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Disposed;
+
+impl fmt::Display for Disposed {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("the model has been disposed")
+  }
+}
+
+impl std::error::Error for Disposed {}
@@ -0,0 +1,677 @@
+//! Persisting models across page loads.
+//!
+//! Enabled with the `persist` feature. Annotating a model with
+//! `#[model(persist(key = "..."))]` loads its initial state from a
+//! [`Storage`] when calling `init()`, and saves a snapshot back
+//! after the update function runs.
+//!
+//! ```rust
+//! use leptos_tea::Cmd;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize, leptos_tea::Model)]
+//! #[model(persist(key = "cart"))]
+//! struct CartModel {
+//!   items: Vec<String>,
+//! }
+//! ```
+//!
+//! By default, models are persisted to [`LocalStorage`] in the
+//! browser, and to a shared [`MemoryStorage`] everywhere else.
+//! A different backend can be provided with
+//! `#[model(persist(key = "...", storage = expr))]`, where `expr`
+//! evaluates to something implementing [`Storage`].
+//!
+//! # Versioning
+//!
+//! Models are stored along with a schema version, which is `0` unless
+//! set with `#[model(version = N)]`. When the stored version is older,
+//! the function set with `#[model(migrate = path)]` is called once per
+//! version to upgrade the stored JSON before deserializing it.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use serde_json::{json, Value};
+//!
+//! #[derive(Default, Serialize, Deserialize, leptos_tea::Model)]
+//! #[model(persist(key = "user"), version = 1, migrate = migrate_user)]
+//! struct UserModel {
+//!   display_name: String,
+//! }
+//!
+//! /// Upgrades `value` from version `from` to `from + 1`.
+//! fn migrate_user(from: u32, mut value: Value) -> Result<Value, String> {
+//!   match from {
+//!     // `name` was renamed to `display_name`
+//!     0 => {
+//!       let name = value["name"].take();
+//!
+//!       Ok(json!({ "display_name": name }))
+//!     }
+//!     _ => Err(format!("unknown version {from}")),
+//!   }
+//! }
+//! ```
+//!
+//! When nothing is stored, the model `init()` was called on is used.
+//! If loading or migrating fails, the error is logged and
+//! `Default::default()` is used instead, so persisted models need to
+//! implement [`Default`]. If the stored model was saved with a newer
+//! version, such as by another tab running a newer build of the app,
+//! the model also isn't saved until the page is reloaded, so the
+//! newer data isn't overwritten.
+
+use leptos_reactive::{spawn_local, spawn_local_with_owner, untrack, Owner};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+  cell::{Cell, RefCell},
+  collections::HashMap,
+  fmt,
+  rc::Rc,
+};
+
+/// A key-value store models can be persisted to.
+pub trait Storage {
+  /// Returns the value stored under `key`, if any.
+  fn get(&self, key: &str) -> Result<Option<String>, Error>;
+
+  /// Stores `value` under `key`, replacing any previous value.
+  fn set(&self, key: &str, value: &str) -> Result<(), Error>;
+
+  /// Removes the value stored under `key`.
+  fn remove(&self, key: &str) -> Result<(), Error>;
+}
+
+impl<S: Storage + ?Sized> Storage for Rc<S> {
+  fn get(&self, key: &str) -> Result<Option<String>, Error> {
+    (**self).get(key)
+  }
+
+  fn set(&self, key: &str, value: &str) -> Result<(), Error> {
+    (**self).set(key, value)
+  }
+
+  fn remove(&self, key: &str) -> Result<(), Error> {
+    (**self).remove(key)
+  }
+}
+
+/// Errors which can occur while loading or saving a model.
+#[derive(Debug)]
+pub enum Error {
+  /// The storage backend failed.
+  Storage(String),
+  /// The model could not be serialized or deserialized.
+  Serde(serde_json::Error),
+  /// The stored model was saved by a newer version of the app.
+  UnknownVersion {
+    /// The version of the stored model.
+    stored: u32,
+    /// The current version of the model.
+    current: u32,
+  },
+  /// The stored model could not be upgraded to the next version.
+  Migration {
+    /// The version being migrated from.
+    from: u32,
+    /// Why the migration failed.
+    message: String,
+  },
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Storage(err) => write!(f, "storage error: {err}"),
+      Self::Serde(err) => write!(f, "serialization error: {err}"),
+      Self::UnknownVersion { stored, current } => write!(
+        f,
+        "stored model has version {stored}, which is newer than the \
+         current version {current}"
+      ),
+      Self::Migration { from, message } => write!(
+        f,
+        "failed to migrate model from version {from} to {}: {message}",
+        from + 1
+      ),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::Serde(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<serde_json::Error> for Error {
+  fn from(err: serde_json::Error) -> Self {
+    Self::Serde(err)
+  }
+}
+
+/// Stores values in memory.
+///
+/// Clones share the same underlying map.
+#[derive(Clone, Default)]
+pub struct MemoryStorage(Rc<RefCell<HashMap<String, String>>>);
+
+impl MemoryStorage {
+  /// Creates an empty storage.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns the storage shared by every model on this thread which
+  /// doesn't specify one, when not running in the browser.
+  pub fn shared() -> Self {
+    thread_local! {
+      static SHARED: MemoryStorage = MemoryStorage::new();
+    }
+
+    SHARED.with(Clone::clone)
+  }
+}
+
+impl Storage for MemoryStorage {
+  fn get(&self, key: &str) -> Result<Option<String>, Error> {
+    Ok(self.0.borrow().get(key).cloned())
+  }
+
+  fn set(&self, key: &str, value: &str) -> Result<(), Error> {
+    self.0.borrow_mut().insert(key.to_owned(), value.to_owned());
+
+    Ok(())
+  }
+
+  fn remove(&self, key: &str) -> Result<(), Error> {
+    self.0.borrow_mut().remove(key);
+
+    Ok(())
+  }
+}
+
+/// Stores values in the browser's `localStorage`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalStorage;
+
+/// Stores values in the browser's `sessionStorage`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SessionStorage;
+
+fn web_storage<E: fmt::Debug>(
+  storage: impl FnOnce(&web_sys::Window) -> Result<Option<web_sys::Storage>, E>,
+) -> Result<web_sys::Storage, Error> {
+  let window = web_sys::window()
+    .ok_or_else(|| Error::Storage("`window` is not available".into()))?;
+
+  storage(&window)
+    .map_err(js_error)?
+    .ok_or_else(|| Error::Storage("storage is not available".into()))
+}
+
+fn js_error(err: impl fmt::Debug) -> Error {
+  Error::Storage(format!("{err:?}"))
+}
+
+macro_rules! impl_web_storage {
+  ($ty:ty, $storage:ident) => {
+    impl Storage for $ty {
+      fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        web_storage(web_sys::Window::$storage)?
+          .get_item(key)
+          .map_err(js_error)
+      }
+
+      fn set(&self, key: &str, value: &str) -> Result<(), Error> {
+        web_storage(web_sys::Window::$storage)?
+          .set_item(key, value)
+          .map_err(js_error)
+      }
+
+      fn remove(&self, key: &str) -> Result<(), Error> {
+        web_storage(web_sys::Window::$storage)?
+          .remove_item(key)
+          .map_err(js_error)
+      }
+    }
+  };
+}
+
+impl_web_storage!(LocalStorage, local_storage);
+impl_web_storage!(SessionStorage, session_storage);
+
+/// Stores each value in its own file inside of a directory.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Debug)]
+pub struct FileStorage {
+  dir: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+  /// Stores values inside of `dir`, which is created if it doesn't
+  /// exist when the first value is saved.
+  pub fn new(dir: impl Into<std::path::PathBuf>) -> Self {
+    Self { dir: dir.into() }
+  }
+
+  fn path(&self, key: &str) -> std::path::PathBuf {
+    self.dir.join(format!("{key}.json"))
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+  fn get(&self, key: &str) -> Result<Option<String>, Error> {
+    match std::fs::read_to_string(self.path(key)) {
+      Ok(value) => Ok(Some(value)),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+      Err(err) => Err(Error::Storage(err.to_string())),
+    }
+  }
+
+  fn set(&self, key: &str, value: &str) -> Result<(), Error> {
+    std::fs::create_dir_all(&self.dir)
+      .and_then(|_| std::fs::write(self.path(key), value))
+      .map_err(|err| Error::Storage(err.to_string()))
+  }
+
+  fn remove(&self, key: &str) -> Result<(), Error> {
+    match std::fs::remove_file(self.path(key)) {
+      Ok(_) => Ok(()),
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+      Err(err) => Err(Error::Storage(err.to_string())),
+    }
+  }
+}
+
+#[doc(hidden)]
+/// The storage used when none is specified.
+pub fn default_storage() -> Rc<dyn Storage> {
+  if cfg!(target_arch = "wasm32") {
+    Rc::new(LocalStorage)
+  } else {
+    Rc::new(MemoryStorage::shared())
+  }
+}
+
+/// A stored model, along with the version of its schema.
+///
+/// The version is stored under a key models are unlikely to have, so
+/// models saved before versioning are never mistaken for a payload.
+#[derive(Serialize, Deserialize)]
+struct Payload<M> {
+  #[serde(rename = "$leptos_tea_version")]
+  version: u32,
+  model: M,
+}
+
+/// Key [`Payload::version`] is serialized under.
+const VERSION_KEY: &str = "$leptos_tea_version";
+
+type Migrate = Box<dyn Fn(u32, Value) -> Result<Value, String>>;
+
+#[doc(hidden)]
+/// Loads and saves a model.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`](crate::Model) derive macro.
+pub struct Persister {
+  storage: Rc<dyn Storage>,
+  key: &'static str,
+  version: u32,
+  migrate: Option<Migrate>,
+  owner: Option<Owner>,
+  is_save_pending: Rc<Cell<bool>>,
+  /// Set when the stored model has a newer version than this one.
+  is_read_only: Cell<bool>,
+}
+
+impl Persister {
+  /// Persists the model to `storage` under `key`.
+  pub fn new(storage: impl Storage + 'static, key: &'static str) -> Self {
+    Self {
+      storage: Rc::new(storage),
+      key,
+      version: 0,
+      migrate: None,
+      owner: Owner::current(),
+      is_save_pending: Default::default(),
+      is_read_only: Default::default(),
+    }
+  }
+
+  /// Sets the current version of the model's schema.
+  pub fn version(mut self, version: u32) -> Self {
+    self.version = version;
+
+    self
+  }
+
+  /// Sets the function used to upgrade stored models by one version.
+  pub fn migrate<E: fmt::Display>(
+    mut self,
+    migrate: impl Fn(u32, Value) -> Result<Value, E> + 'static,
+  ) -> Self {
+    self.migrate = Some(Box::new(move |from, value| {
+      migrate(from, value).map_err(|err| err.to_string())
+    }));
+
+    self
+  }
+
+  /// Loads the stored model, if there is one, or the default model
+  /// if it fails to load.
+  ///
+  /// Models stored with a newer version are never saved over.
+  pub fn load<M: DeserializeOwned + Default>(&self) -> Option<M> {
+    self.try_load().unwrap_or_else(|err| {
+      if matches!(err, Error::UnknownVersion { .. }) {
+        self.is_read_only.set(true);
+
+        tracing::warn!(
+          "failed to load model `{}`: {err}; it won't be saved, so the \
+           stored model isn't overwritten",
+          self.key,
+        );
+      } else {
+        tracing::warn!("failed to load model `{}`: {err}", self.key);
+      }
+
+      Some(M::default())
+    })
+  }
+
+  fn try_load<M: DeserializeOwned>(&self) -> Result<Option<M>, Error> {
+    let Some(value) = self.storage.get(self.key)? else {
+      return Ok(None);
+    };
+
+    let value = serde_json::from_str::<Value>(&value)?;
+
+    // Models saved before versioning are treated as version 0
+    let Payload { version, mut model } = if value.get(VERSION_KEY).is_some() {
+      serde_json::from_value::<Payload<Value>>(value)?
+    } else {
+      Payload {
+        version: 0,
+        model: value,
+      }
+    };
+
+    if version > self.version {
+      return Err(Error::UnknownVersion {
+        stored: version,
+        current: self.version,
+      });
+    }
+
+    for from in version..self.version {
+      let Some(migrate) = &self.migrate else {
+        return Err(Error::Migration {
+          from,
+          message: "no `migrate` function was provided".into(),
+        });
+      };
+
+      model = migrate(from, model)
+        .map_err(|message| Error::Migration { from, message })?;
+    }
+
+    Ok(Some(serde_json::from_value(model)?))
+  }
+
+  /// Saves the model on the next tick, so that many updates in a row
+  /// only result in a single write.
+  pub fn save_later<M: Serialize>(
+    &self,
+    snapshot: impl FnOnce() -> M + 'static,
+  ) {
+    if self.is_read_only.get() || self.is_save_pending.replace(true) {
+      return;
+    }
+
+    let storage = self.storage.clone();
+    let key = self.key;
+    let version = self.version;
+    let is_save_pending = self.is_save_pending.clone();
+
+    let save = async move {
+      is_save_pending.set(false);
+
+      if let Err(err) = save(&*storage, key, version, untrack(snapshot)) {
+        tracing::warn!("failed to save model `{key}`: {err}");
+      }
+    };
+
+    // Scoped to the owner so the model isn't read after being disposed
+    if let Some(owner) = self.owner {
+      spawn_local_with_owner(owner, save);
+    } else {
+      spawn_local(save);
+    }
+  }
+}
+
+fn save<M: Serialize>(
+  storage: &dyn Storage,
+  key: &str,
+  version: u32,
+  model: M,
+) -> Result<(), Error> {
+  let value = serde_json::to_string(&Payload { version, model })?;
+
+  storage.set(key, &value)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+  struct Model {
+    name: String,
+    count: usize,
+  }
+
+  fn model() -> Model {
+    Model {
+      name: "leptos".into(),
+      count: 3,
+    }
+  }
+
+  /// A directory of its own for each test, since they run in parallel.
+  fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir()
+      .join(format!("leptos_tea_{}_{name}", std::process::id()));
+
+    let _ = std::fs::remove_dir_all(&dir);
+
+    dir
+  }
+
+  fn round_trip(storage: impl Storage + Clone + 'static) {
+    let persister = Persister::new(storage.clone(), "model");
+
+    assert_eq!(persister.load::<Model>(), None);
+
+    save(&storage, "model", 0, model()).unwrap();
+
+    assert_eq!(persister.load::<Model>(), Some(model()));
+
+    storage.remove("model").unwrap();
+
+    assert_eq!(persister.load::<Model>(), None);
+  }
+
+  #[test]
+  fn memory_storage_round_trip() {
+    round_trip(MemoryStorage::new());
+  }
+
+  #[test]
+  fn memory_storage_clones_share_values() {
+    let storage = MemoryStorage::new();
+
+    storage.clone().set("key", "value").unwrap();
+
+    assert_eq!(storage.get("key").unwrap().as_deref(), Some("value"));
+  }
+
+  #[test]
+  fn file_storage_round_trip() {
+    let dir = temp_dir("round_trip");
+
+    round_trip(FileStorage::new(&dir));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn file_storage_saves_to_file() {
+    let dir = temp_dir("saves_to_file");
+    let storage = FileStorage::new(&dir);
+
+    save(&storage, "model", 0, model()).unwrap();
+
+    let value = std::fs::read_to_string(dir.join("model.json")).unwrap();
+
+    assert!(value.contains("leptos"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn file_storage_missing_file() {
+    let dir = temp_dir("missing_file");
+    let storage = FileStorage::new(&dir);
+
+    assert_eq!(storage.get("model").unwrap(), None);
+    assert!(storage.remove("model").is_ok());
+    assert_eq!(Persister::new(storage, "model").load::<Model>(), None);
+  }
+
+  #[test]
+  fn corrupt_payload_loads_default() {
+    let dir = temp_dir("corrupt_payload");
+    let storage = FileStorage::new(&dir);
+    let persister = Persister::new(storage.clone(), "model");
+
+    storage.set("model", "{ not json").unwrap();
+
+    assert!(matches!(
+      persister.try_load::<Model>(),
+      Err(Error::Serde(_))
+    ));
+    assert_eq!(persister.load::<Model>(), Some(Model::default()));
+
+    // Valid JSON, but not a model
+    storage.set("model", r#"{ "name": 3 }"#).unwrap();
+
+    assert!(matches!(
+      persister.try_load::<Model>(),
+      Err(Error::Serde(_))
+    ));
+    assert_eq!(persister.load::<Model>(), Some(Model::default()));
+
+    std::fs::remove_dir_all(dir).unwrap();
+  }
+
+  #[test]
+  fn unversioned_payload_is_version_0() {
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Legacy {
+      version: String,
+      model: String,
+    }
+
+    let storage = MemoryStorage::new();
+
+    // Has `version` and `model` keys of its own
+    storage
+      .set("legacy", r#"{ "version": "v1", "model": "m" }"#)
+      .unwrap();
+
+    let persister = Persister::new(storage, "legacy");
+
+    assert_eq!(
+      persister.load::<Legacy>(),
+      Some(Legacy {
+        version: "v1".into(),
+        model: "m".into(),
+      })
+    );
+  }
+
+  #[test]
+  fn failed_migration_loads_default() {
+    let storage = MemoryStorage::new();
+
+    save(&storage, "model", 0, model()).unwrap();
+
+    let persister = Persister::new(storage, "model")
+      .version(1)
+      .migrate(|_, _| Err("unsupported"));
+
+    assert!(matches!(
+      persister.try_load::<Model>(),
+      Err(Error::Migration { from: 0, .. })
+    ));
+    assert_eq!(persister.load::<Model>(), Some(Model::default()));
+  }
+
+  #[test]
+  fn migrates_older_versions() {
+    let storage = MemoryStorage::new();
+
+    save(
+      &storage,
+      "model",
+      0,
+      serde_json::json!({ "name": "leptos" }),
+    )
+    .unwrap();
+
+    let persister =
+      Persister::new(storage, "model")
+        .version(1)
+        .migrate(|_, mut value| {
+          value["count"] = 3.into();
+
+          Ok::<_, String>(value)
+        });
+
+    assert_eq!(persister.load::<Model>(), Some(model()));
+  }
+
+  #[test]
+  fn newer_versions_are_not_saved_over() {
+    let storage = MemoryStorage::new();
+
+    save(&storage, "model", 2, model()).unwrap();
+
+    let persister = Persister::new(storage.clone(), "model").version(1);
+
+    assert!(matches!(
+      persister.try_load::<Model>(),
+      Err(Error::UnknownVersion {
+        stored: 2,
+        current: 1
+      })
+    ));
+    assert_eq!(persister.load::<Model>(), Some(Model::default()));
+
+    persister.save_later(Model::default);
+
+    assert_eq!(
+      Persister::new(storage, "model").version(2).load::<Model>(),
+      Some(model())
+    );
+  }
+}
@@ -0,0 +1,725 @@
+use crate::{Batching, Cmd, Envelope, MsgDispatcher, Options, Waiter};
+use futures::{channel::mpsc::UnboundedSender, StreamExt};
+use leptos_reactive::*;
+use std::{
+  cell::{Cell, RefCell},
+  collections::VecDeque,
+  rc::Rc,
+};
+
+#[doc(hidden)]
+/// Creates the message channel and starts listening for messages.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`](crate::Model) derive macro.
+#[track_caller]
+pub fn init<Msg: Default + 'static>(
+  update_fn: impl Fn(Msg, Cmd<Msg>) + 'static,
+  options: Options<Msg>,
+) -> (MsgDispatcher<Msg>, ProgramHandle) {
+  let (msg_dispatcher, handle, _) =
+    start(update_fn, options, Some(Envelope::new(Msg::default())));
+
+  (msg_dispatcher, handle)
+}
+
+/// Same as [`init`], but allows choosing the first message, if any,
+/// and returns the program itself for internal use.
+#[track_caller]
+pub(crate) fn start<Msg: 'static>(
+  update_fn: impl Fn(Msg, Cmd<Msg>) + 'static,
+  options: Options<Msg>,
+  first_msg: Option<Envelope<Msg>>,
+) -> (MsgDispatcher<Msg>, ProgramHandle, Rc<Program<Msg>>) {
+  let Options {
+    on_dispose,
+    batching,
+  } = options;
+
+  let owner = Owner::current().expect(
+    "`Model::init()` must be called  within the context of an `Owner`\n\ntry \
+     calling `Model::init()` at the top level of the component definition",
+  );
+
+  let (tx, mut rx) = futures::channel::mpsc::unbounded();
+
+  if let Some(envelope) = first_msg {
+    tx.unbounded_send(envelope).unwrap();
+  }
+
+  let tx = store_value(tx);
+
+  let (remote_tx, mut remote_rx) = futures::channel::mpsc::unbounded();
+
+  on_cleanup({
+    let remote_tx = remote_tx.clone();
+
+    move || remote_tx.close_channel()
+  });
+
+  let remote_tx = store_value(remote_tx);
+
+  let program = Rc::new(Program {
+    update_fn: Box::new(update_fn),
+    owner,
+    tx,
+    remote_tx,
+    batching,
+    status: Cell::new(Status::Running),
+    queue: Default::default(),
+    is_flushing: Cell::new(false),
+  });
+
+  if let Some(msg) = on_dispose {
+    let program = program.clone();
+
+    on_cleanup(move || {
+      if program.status.get() != Status::Stopped {
+        program.handle(Envelope::new(msg));
+      }
+    });
+  }
+
+  let handle = ProgramHandle(store_value(program.clone() as Rc<dyn Control>));
+
+  // Messages from remote dispatchers are forwarded to the local
+  // channel, so they are handled in order with everything else
+  spawn_local_with_owner(owner, async move {
+    while let Some(msg) = remote_rx.next().await {
+      if let Some(tx) = tx.try_get_value() {
+        let _ = tx.unbounded_send(Envelope::new(msg));
+      }
+    }
+  });
+
+  spawn_local_with_owner(owner, {
+    let program = program.clone();
+
+    async move {
+      while let Some(envelope) = rx.next().await {
+        if program.status.get() == Status::Stopped {
+          break;
+        }
+
+        program.queue.borrow_mut().push_back(envelope);
+
+        program.flush();
+      }
+    }
+  });
+
+  (MsgDispatcher { tx, remote_tx }, handle, program)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+  Running,
+  Paused,
+  Stopped,
+}
+
+/// The running message loop of a model.
+pub(crate) struct Program<Msg: 'static> {
+  update_fn: Box<dyn Fn(Msg, Cmd<Msg>)>,
+  owner: Owner,
+  tx: StoredValue<UnboundedSender<Envelope<Msg>>>,
+  remote_tx: StoredValue<UnboundedSender<Msg>>,
+  batching: Batching,
+  status: Cell<Status>,
+  /// Messages received but not yet handled, usually because
+  /// the program is paused.
+  queue: RefCell<VecDeque<Envelope<Msg>>>,
+  is_flushing: Cell<bool>,
+}
+
+impl<Msg: 'static> Program<Msg> {
+  pub(crate) fn handle(&self, Envelope { msg, waiter }: Envelope<Msg>) {
+    let cmd = Cmd::new(
+      self.owner,
+      self.tx,
+      waiter.as_ref().and_then(Waiter::settled),
+    );
+
+    if self.batching == Batching::PerMessage {
+      batch(|| (self.update_fn)(msg, cmd));
+    } else {
+      (self.update_fn)(msg, cmd);
+    }
+
+    if let Some(waiter) = waiter {
+      waiter.processed();
+    }
+  }
+
+  /// Runs the update function without performing any of the
+  /// resulting messages or commands.
+  #[cfg_attr(
+    not(all(feature = "hydrate", not(feature = "ssr"))),
+    allow(dead_code)
+  )]
+  pub(crate) fn replay(&self, msg: Msg) {
+    (self.update_fn)(msg, Cmd::discarding(self.owner));
+  }
+
+  /// Handles queued messages for as long as the program is running.
+  fn flush(&self) {
+    // Already flushing further up the stack, such as when the update
+    // function resumes the program, which will pick up where it left off
+    if self.is_flushing.replace(true) {
+      return;
+    }
+
+    self.batched(|| {
+      while self.status.get() == Status::Running {
+        let Some(envelope) = self.queue.borrow_mut().pop_front() else {
+          break;
+        };
+
+        self.handle(envelope);
+      }
+    });
+
+    self.is_flushing.set(false);
+  }
+
+  /// Runs `f` in a batch if the program batches per flush.
+  fn batched<T>(&self, f: impl FnOnce() -> T) -> T {
+    if self.batching == Batching::PerFlush {
+      batch(f)
+    } else {
+      f()
+    }
+  }
+}
+
+/// Type-erased controls for a [`Program`], so [`ProgramHandle`]
+/// doesn't need to know about `Msg`.
+trait Control {
+  fn pause(&self);
+
+  fn resume(&self);
+
+  fn stop(&self);
+
+  fn status(&self) -> Status;
+
+  fn queued(&self) -> usize;
+
+  fn drain(&self) -> usize;
+
+  fn discard(&self) -> usize;
+}
+
+impl<Msg: 'static> Control for Program<Msg> {
+  fn pause(&self) {
+    if self.status.get() == Status::Running {
+      self.status.set(Status::Paused);
+    }
+  }
+
+  fn resume(&self) {
+    if self.status.get() == Status::Paused {
+      self.status.set(Status::Running);
+
+      self.flush();
+    }
+  }
+
+  fn stop(&self) {
+    self.status.set(Status::Stopped);
+
+    self.queue.borrow_mut().clear();
+
+    if let Some(tx) = self.tx.try_get_value() {
+      tx.close_channel();
+    }
+
+    if let Some(remote_tx) = self.remote_tx.try_get_value() {
+      remote_tx.close_channel();
+    }
+  }
+
+  fn status(&self) -> Status {
+    self.status.get()
+  }
+
+  fn queued(&self) -> usize {
+    self.queue.borrow().len()
+  }
+
+  fn drain(&self) -> usize {
+    // Called from within the update function, which can't be run
+    // again while it's still on the stack
+    if self.is_flushing.replace(true) {
+      return 0;
+    }
+
+    let drained = self.batched(|| {
+      let mut drained = 0;
+
+      while self.status.get() != Status::Stopped {
+        let Some(envelope) = self.queue.borrow_mut().pop_front() else {
+          break;
+        };
+
+        self.handle(envelope);
+
+        drained += 1;
+      }
+
+      drained
+    });
+
+    self.is_flushing.set(false);
+
+    drained
+  }
+
+  fn discard(&self) -> usize {
+    let mut queue = self.queue.borrow_mut();
+
+    let discarded = queue.len();
+
+    queue.clear();
+
+    discarded
+  }
+}
+
+/// Allows controlling the message loop of a running model.
+///
+/// Returned by the `init_with_handle` method generated by the
+/// [`Model`](crate::Model) derive macro.
+///
+/// Once the [`Owner`] the model was initialized in is disposed,
+/// all methods on the handle do nothing.
+#[derive(Clone, Copy)]
+pub struct ProgramHandle(StoredValue<Rc<dyn Control>>);
+
+impl ProgramHandle {
+  /// Clones the program out of the stored value, since the update
+  /// function can't run while the stored value is borrowed.
+  fn program(self) -> Option<Rc<dyn Control>> {
+    self.0.try_get_value()
+  }
+
+  /// Stops handling messages until [`ProgramHandle::resume`] is
+  /// called. Messages dispatched in the meantime are queued.
+  pub fn pause(self) {
+    if let Some(program) = self.program() {
+      program.pause();
+    }
+  }
+
+  /// Resumes a paused model, handling any queued messages.
+  pub fn resume(self) {
+    if let Some(program) = self.program() {
+      program.resume();
+    }
+  }
+
+  /// Permanently stops the message loop.
+  ///
+  /// Queued messages are dropped, and messages dispatched
+  /// afterwards are ignored.
+  pub fn stop(self) {
+    if let Some(program) = self.program() {
+      program.stop();
+    }
+  }
+
+  /// Returns `true` if the model is currently handling messages.
+  pub fn is_running(self) -> bool {
+    self
+      .program()
+      .map(|program| program.status() == Status::Running)
+      .unwrap_or_default()
+  }
+
+  /// Returns `true` if the model is paused.
+  pub fn is_paused(self) -> bool {
+    self
+      .program()
+      .map(|program| program.status() == Status::Paused)
+      .unwrap_or_default()
+  }
+
+  /// Returns `true` if the model was stopped or disposed.
+  pub fn is_stopped(self) -> bool {
+    self
+      .program()
+      .map(|program| program.status() == Status::Stopped)
+      .unwrap_or(true)
+  }
+
+  /// Returns the number of messages waiting to be handled.
+  pub fn queued(self) -> usize {
+    self
+      .program()
+      .map(|program| program.queued())
+      .unwrap_or_default()
+  }
+
+  /// Immediately handles every queued message, even if the model
+  /// is paused. Returns the number of messages handled.
+  ///
+  /// Does nothing when called from within the update function.
+  pub fn drain(self) -> usize {
+    self
+      .program()
+      .map(|program| program.drain())
+      .unwrap_or_default()
+  }
+
+  /// Drops every queued message without handling it. Returns the
+  /// number of messages dropped.
+  pub fn discard(self) -> usize {
+    self
+      .program()
+      .map(|program| program.discard())
+      .unwrap_or_default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Effects don't run on the server
+  #[cfg(not(feature = "ssr"))]
+  mod batching {
+    use super::*;
+
+    /// Builds the program directly rather than with [`start`], so
+    /// messages can be queued and flushed without an executor.
+    fn program(
+      batching: Batching,
+      update_fn: impl Fn(usize, Cmd<usize>) + 'static,
+    ) -> Program<usize> {
+      let (tx, _) = futures::channel::mpsc::unbounded();
+      let (remote_tx, _) = futures::channel::mpsc::unbounded();
+
+      Program {
+        update_fn: Box::new(update_fn),
+        owner: Owner::current().unwrap(),
+        tx: store_value(tx),
+        remote_tx: store_value(remote_tx),
+        batching,
+        status: Cell::new(Status::Running),
+        queue: Default::default(),
+        is_flushing: Cell::new(false),
+      }
+    }
+
+    /// Handles `msgs` at once, returning how many times an effect
+    /// observing both signals written by the update function ran.
+    fn effect_runs(batching: Batching, msgs: &[usize]) -> usize {
+      let runtime = create_runtime();
+
+      let start = create_rw_signal(0);
+      let end = create_rw_signal(0);
+
+      let runs = Rc::new(Cell::new(0));
+
+      create_effect({
+        let runs = runs.clone();
+
+        move |_| {
+          start.track();
+          end.track();
+
+          runs.set(runs.get() + 1);
+        }
+      });
+
+      let program = program(batching, move |by, _| {
+        start.update(|start| *start += by);
+        end.update(|end| *end += by);
+      });
+
+      program
+        .queue
+        .borrow_mut()
+        .extend(msgs.iter().copied().map(Envelope::new));
+
+      program.flush();
+
+      assert_eq!(start.get_untracked(), msgs.iter().sum::<usize>());
+      assert_eq!(end.get_untracked(), msgs.iter().sum::<usize>());
+
+      let runs = runs.get();
+
+      runtime.dispose();
+
+      runs
+    }
+
+    #[test]
+    fn no_batching_runs_effects_per_write() {
+      // Once initially, then once per write
+      assert_eq!(effect_runs(Batching::None, &[1, 2]), 5);
+    }
+
+    #[test]
+    fn per_message_runs_effects_per_message() {
+      // Once initially, then once per message
+      assert_eq!(effect_runs(Batching::PerMessage, &[1, 2]), 3);
+    }
+
+    #[test]
+    fn per_flush_runs_effects_per_flush() {
+      // Once initially, then once for both messages
+      assert_eq!(effect_runs(Batching::PerFlush, &[1, 2]), 2);
+    }
+
+    #[test]
+    fn paused_messages_are_batched_on_resume() {
+      let runtime = create_runtime();
+
+      let count = create_rw_signal(0);
+
+      let runs = Rc::new(Cell::new(0));
+
+      create_effect({
+        let runs = runs.clone();
+
+        move |_| {
+          count.track();
+
+          runs.set(runs.get() + 1);
+        }
+      });
+
+      let program = program(Batching::PerFlush, move |by, _| {
+        count.update(|count| *count += by);
+      });
+
+      program.pause();
+
+      program
+        .queue
+        .borrow_mut()
+        .extend([1, 2, 3].map(Envelope::new));
+
+      program.flush();
+
+      assert_eq!(runs.get(), 1);
+
+      program.resume();
+
+      assert_eq!(count.get_untracked(), 6);
+      assert_eq!(runs.get(), 2);
+
+      runtime.dispose();
+    }
+  }
+
+  // Spawned tasks only run on the server outside of the browser,
+  // where they need a `LocalSet`
+  #[cfg(feature = "ssr")]
+  mod message_loop {
+    use super::*;
+    use crate::{Disposed, Reply};
+    use std::future::Future;
+
+    #[derive(Default)]
+    enum Msg {
+      #[default]
+      Init,
+      Add(usize),
+      /// Adds once a command finishes.
+      AddLater(usize),
+      /// Spawns a command which never finishes.
+      Hang,
+      Count(Reply<usize>),
+      /// Drops the reply without answering.
+      Ignore(Reply<usize>),
+    }
+
+    fn counter(count: Rc<Cell<usize>>) -> impl Fn(Msg, Cmd<Msg>) {
+      move |msg, mut cmd| match msg {
+        Msg::Init => {}
+        Msg::Add(by) => count.set(count.get() + by),
+        Msg::AddLater(by) => cmd.cmd(async move {
+          tokio::task::yield_now().await;
+
+          Some(Msg::Add(by))
+        }),
+        Msg::Hang => cmd.cmd(futures::future::pending::<Option<Msg>>()),
+        Msg::Count(reply) => reply.send(count.get()),
+        Msg::Ignore(_) => {}
+      }
+    }
+
+    /// Runs `f` within a new runtime, on a `LocalSet` so the program
+    /// can spawn its message loop.
+    async fn run<F: Future<Output = ()>>(f: impl FnOnce() -> F) {
+      let runtime = create_runtime();
+
+      tokio::task::LocalSet::new()
+        .run_until(async move { f().await })
+        .await;
+
+      runtime.dispose();
+    }
+
+    /// Lets every spawned task run until it's waiting on something.
+    async fn yield_to_tasks() {
+      for _ in 0..10 {
+        tokio::task::yield_now().await;
+      }
+    }
+
+    #[tokio::test]
+    async fn dispatch_and_wait_resolves_once_handled() {
+      run(|| async {
+        let count = Rc::new(Cell::new(0));
+
+        let (msg_dispatcher, _) =
+          init(counter(count.clone()), Options::default());
+
+        msg_dispatcher.dispatch_and_wait(Msg::Add(1)).await;
+
+        assert_eq!(count.get(), 1);
+
+        // Commands aren't waited on
+        msg_dispatcher.dispatch_and_wait(Msg::Hang).await;
+      })
+      .await;
+    }
+
+    #[tokio::test]
+    async fn dispatch_and_settle_resolves_once_commands_finish() {
+      run(|| async {
+        let count = Rc::new(Cell::new(0));
+
+        let (msg_dispatcher, _) =
+          init(counter(count.clone()), Options::default());
+
+        msg_dispatcher.dispatch_and_settle(Msg::AddLater(2)).await;
+
+        assert_eq!(count.get(), 2);
+      })
+      .await;
+    }
+
+    #[tokio::test]
+    async fn ask_resolves_to_the_reply() {
+      run(|| async {
+        let count = Rc::new(Cell::new(0));
+
+        let (msg_dispatcher, _) =
+          init(counter(count.clone()), Options::default());
+
+        msg_dispatcher.dispatch(Msg::Add(3));
+
+        assert_eq!(msg_dispatcher.ask(Msg::Count).await, Ok(3));
+        assert_eq!(msg_dispatcher.ask(Msg::Ignore).await, Err(Disposed));
+      })
+      .await;
+    }
+
+    #[tokio::test]
+    async fn on_dispose_is_handled_synchronously_on_cleanup() {
+      run(|| async {
+        let count = Rc::new(Cell::new(0));
+
+        let rerun = create_rw_signal(());
+
+        // Memos clean up after their previous run when rerunning, so
+        // the model is disposed without disposing the runtime
+        let owner = create_memo({
+          let count = count.clone();
+
+          move |_| {
+            rerun.track();
+
+            init(
+              counter(count.clone()),
+              Options::default().on_dispose(Msg::Add(10)),
+            );
+          }
+        });
+
+        owner.get();
+
+        rerun.set(());
+
+        owner.get();
+
+        assert_eq!(count.get(), 10);
+      })
+      .await;
+    }
+
+    #[tokio::test]
+    async fn handle_pauses_resumes_and_stops() {
+      run(|| async {
+        let count = Rc::new(Cell::new(0));
+
+        let (msg_dispatcher, handle) =
+          init(counter(count.clone()), Options::default());
+
+        handle.pause();
+
+        msg_dispatcher.dispatch(Msg::Add(1));
+
+        yield_to_tasks().await;
+
+        assert!(handle.is_paused());
+        assert_eq!(handle.queued(), 1);
+        assert_eq!(count.get(), 0);
+
+        handle.resume();
+
+        assert!(handle.is_running());
+        assert_eq!(handle.queued(), 0);
+        assert_eq!(count.get(), 1);
+
+        handle.stop();
+
+        msg_dispatcher.dispatch(Msg::Add(1));
+
+        yield_to_tasks().await;
+
+        assert!(handle.is_stopped());
+        assert!(msg_dispatcher.is_disposed());
+        assert_eq!(count.get(), 1);
+      })
+      .await;
+    }
+
+    #[tokio::test]
+    async fn remote_dispatcher_reports_disposal() {
+      run(|| async {
+        let count = Rc::new(Cell::new(0));
+
+        let (msg_dispatcher, handle) =
+          init(counter(count.clone()), Options::default());
+
+        let remote = msg_dispatcher.remote();
+
+        std::thread::spawn({
+          let remote = remote.clone();
+
+          move || remote.dispatch(Msg::Add(1))
+        })
+        .join()
+        .unwrap()
+        .unwrap();
+
+        yield_to_tasks().await;
+
+        assert_eq!(count.get(), 1);
+        assert!(!remote.is_disposed());
+
+        handle.stop();
+
+        assert!(remote.is_disposed());
+        assert_eq!(remote.dispatch(Msg::Add(1)), Err(Disposed));
+        assert!(msg_dispatcher.remote().is_disposed());
+      })
+      .await;
+    }
+  }
+}
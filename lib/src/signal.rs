@@ -0,0 +1,268 @@
+use leptos_reactive::*;
+use std::fmt;
+
+/// A [`RwSignal`] which only notifies subscribers when its value
+/// actually changes, as determined by [`PartialEq`].
+///
+/// Used by the update model for fields annotated with
+/// `#[model(eq)]`, or for every field when the model itself is
+/// annotated.
+///
+/// Since the value can't be compared with its previous value once
+/// it was mutated in place, [`SignalUpdate::update`] clones the whole
+/// value on every call. For large values, prefer [`SignalSet::set`],
+/// or [`EqSignal::signal`] to update without comparing.
+///
+/// ```rust
+/// # use leptos_tea::Cmd;
+/// #[derive(Default, leptos_tea::Model)]
+/// #[model(eq)]
+/// struct SearchModel {
+///   query: String,
+///   #[model(eq)]
+///   page: usize,
+/// }
+/// ```
+pub struct EqSignal<T: 'static>(RwSignal<T>);
+
+impl<T> Clone for EqSignal<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for EqSignal<T> {}
+
+impl<T> PartialEq for EqSignal<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.0 == other.0
+  }
+}
+
+impl<T> Eq for EqSignal<T> {}
+
+impl<T> std::hash::Hash for EqSignal<T> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.0.hash(state);
+  }
+}
+
+impl<T> From<RwSignal<T>> for EqSignal<T> {
+  fn from(signal: RwSignal<T>) -> Self {
+    Self(signal)
+  }
+}
+
+impl<T> EqSignal<T> {
+  /// Returns a read-only handle to the signal.
+  pub fn read_only(&self) -> ReadSignal<T> {
+    self.0.read_only()
+  }
+
+  /// Returns the underlying [`RwSignal`], which notifies subscribers
+  /// on every write.
+  pub fn signal(&self) -> RwSignal<T> {
+    self.0
+  }
+}
+
+impl<T: Clone> SignalGet<T> for EqSignal<T> {
+  fn get(&self) -> T {
+    self.0.get()
+  }
+
+  fn try_get(&self) -> Option<T> {
+    self.0.try_get()
+  }
+}
+
+impl<T> SignalWith<T> for EqSignal<T> {
+  fn with<O>(&self, f: impl FnOnce(&T) -> O) -> O {
+    self.0.with(f)
+  }
+
+  fn try_with<O>(&self, f: impl FnOnce(&T) -> O) -> Option<O> {
+    self.0.try_with(f)
+  }
+}
+
+impl<T: Clone> SignalGetUntracked<T> for EqSignal<T> {
+  fn get_untracked(&self) -> T {
+    self.0.get_untracked()
+  }
+
+  fn try_get_untracked(&self) -> Option<T> {
+    self.0.try_get_untracked()
+  }
+}
+
+impl<T> SignalWithUntracked<T> for EqSignal<T> {
+  fn with_untracked<O>(&self, f: impl FnOnce(&T) -> O) -> O {
+    self.0.with_untracked(f)
+  }
+
+  fn try_with_untracked<O>(&self, f: impl FnOnce(&T) -> O) -> Option<O> {
+    self.0.try_with_untracked(f)
+  }
+}
+
+impl<T: PartialEq> SignalSet<T> for EqSignal<T> {
+  fn set(&self, new_value: T) {
+    let _ = self.try_set(new_value);
+  }
+
+  fn try_set(&self, new_value: T) -> Option<T> {
+    match self.0.try_with_untracked(|value| *value != new_value) {
+      Some(true) => self.0.try_set(new_value),
+      Some(false) => None,
+      None => Some(new_value),
+    }
+  }
+}
+
+/// Clones the value on every update, see [`EqSignal`].
+impl<T: Clone + PartialEq> SignalUpdate<T> for EqSignal<T> {
+  fn update(&self, f: impl FnOnce(&mut T)) {
+    let _ = self.try_update(f);
+  }
+
+  fn try_update<O>(&self, f: impl FnOnce(&mut T) -> O) -> Option<O> {
+    // Updated on a copy, since the signal can't be compared with its
+    // previous value once it was mutated in place
+    let mut value = self.0.try_get_untracked()?;
+
+    let output = f(&mut value);
+
+    self.set(value);
+
+    Some(output)
+  }
+}
+
+/// A read-only handle to a [`StoredValue`].
+///
+/// Used by the view model for fields annotated with
+/// `#[model(store)]`, which only the update model can write to.
+pub struct ReadStoredValue<T: 'static>(StoredValue<T>);
+
+impl<T> Clone for ReadStoredValue<T> {
+  fn clone(&self) -> Self {
+    *self
+  }
+}
+
+impl<T> Copy for ReadStoredValue<T> {}
+
+impl<T> PartialEq for ReadStoredValue<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.0 == other.0
+  }
+}
+
+impl<T> Eq for ReadStoredValue<T> {}
+
+impl<T> From<StoredValue<T>> for ReadStoredValue<T> {
+  fn from(stored_value: StoredValue<T>) -> Self {
+    Self(stored_value)
+  }
+}
+
+impl<T> ReadStoredValue<T> {
+  /// Returns a clone of the current value.
+  ///
+  /// # Panics
+  /// Panics if the value was disposed.
+  pub fn get_value(&self) -> T
+  where
+    T: Clone,
+  {
+    self.0.get_value()
+  }
+
+  /// Same as [`ReadStoredValue::get_value`], but returns `None` if
+  /// the value was disposed.
+  pub fn try_get_value(&self) -> Option<T>
+  where
+    T: Clone,
+  {
+    self.0.try_get_value()
+  }
+
+  /// Applies `f` to the current value.
+  ///
+  /// # Panics
+  /// Panics if the value was disposed.
+  pub fn with_value<O>(&self, f: impl FnOnce(&T) -> O) -> O {
+    self.0.with_value(f)
+  }
+
+  /// Same as [`ReadStoredValue::with_value`], but returns `None` if
+  /// the value was disposed.
+  pub fn try_with_value<O>(&self, f: impl FnOnce(&T) -> O) -> Option<O> {
+    self.0.try_with_value(f)
+  }
+}
+
+/// Untracked writes never notify subscribers, so there is nothing
+/// to compare.
+impl<T> SignalSetUntracked<T> for EqSignal<T> {
+  fn set_untracked(&self, new_value: T) {
+    self.0.set_untracked(new_value);
+  }
+
+  fn try_set_untracked(&self, new_value: T) -> Option<T> {
+    self.0.try_set_untracked(new_value)
+  }
+}
+
+impl<T> SignalUpdateUntracked<T> for EqSignal<T> {
+  fn update_untracked(&self, f: impl FnOnce(&mut T)) {
+    self.0.update_untracked(f);
+  }
+
+  fn try_update_untracked<O>(&self, f: impl FnOnce(&mut T) -> O) -> Option<O> {
+    self.0.try_update_untracked(f)
+  }
+}
+
+#[doc(hidden)]
+/// Formats the current value of a signal, rather than its id.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`](crate::Model) derive macro.
+pub fn debug_signal<T: fmt::Debug>(
+  signal: &impl SignalWithUntracked<T>,
+) -> impl fmt::Debug + '_ {
+  DebugWith(move |f: &mut fmt::Formatter<'_>| {
+    signal
+      .try_with_untracked(|value| fmt::Debug::fmt(value, f))
+      .unwrap_or_else(|| f.write_str("<disposed>"))
+  })
+}
+
+#[doc(hidden)]
+/// Formats the current value of a stored value.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`](crate::Model) derive macro.
+pub fn debug_stored_value<T: fmt::Debug + 'static>(
+  stored_value: impl Into<ReadStoredValue<T>>,
+) -> impl fmt::Debug {
+  let stored_value = stored_value.into();
+
+  DebugWith(move |f: &mut fmt::Formatter<'_>| {
+    stored_value
+      .try_with_value(|value| fmt::Debug::fmt(value, f))
+      .unwrap_or_else(|| f.write_str("<disposed>"))
+  })
+}
+
+struct DebugWith<F>(F);
+
+impl<F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result> fmt::Debug
+  for DebugWith<F>
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    (self.0)(f)
+  }
+}
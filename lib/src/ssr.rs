@@ -0,0 +1,318 @@
+//! Transferring models rendered on the server to the client.
+//!
+//! Annotating a model with `#[model(ssr)]` and enabling the `ssr`
+//! feature on the server and the `hydrate` feature on the client
+//! resumes the model on the client from the state it had on the
+//! server, rather than from the value `init()` was called on.
+//!
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize, leptos_tea::Model)]
+//! #[model(ssr)]
+//! struct ArticleModel {
+//!   title: String,
+//!   body: String,
+//! }
+//! ```
+//!
+//! On the server, the model's state is sent once the initial message,
+//! along with every command and message resulting from it, has been
+//! handled. The state is sent along with leptos resources, so it is
+//! only sent when rendering asynchronously or streaming. Commands
+//! which never finish, such as listening to a websocket, should be
+//! added with [`Cmd::detached_cmd`], so they don't hold back the
+//! response.
+//!
+//! On the client, messages are queued until the state arrives. The
+//! initial message is not sent at all if the state was restored, so
+//! commands such as data fetching aren't repeated.
+//!
+//! # Message logs
+//!
+//! Models which aren't serializable can be annotated with
+//! `#[model(ssr(msg_log))]` instead, which sends every message the
+//! server handled. The client then replays them through the update
+//! function, discarding any resulting messages and commands, so `Msg`
+//! must be serializable instead.
+//!
+//! Without either feature enabled, models are initialized as usual.
+
+#[cfg(any(feature = "ssr", feature = "hydrate"))]
+use crate::Envelope;
+use crate::{Cmd, MsgDispatcher, Options, ProgramHandle};
+use std::{cell::RefCell, marker::PhantomData};
+
+#[doc(hidden)]
+/// Starts the model, transferring its state from the server.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`](crate::Model) derive macro.
+#[track_caller]
+pub fn init<Msg: Default + 'static>(
+  update_fn: impl Fn(Msg, Cmd<Msg>) + 'static,
+  options: Options<Msg>,
+  transfer: impl Transfer<Msg> + 'static,
+) -> (MsgDispatcher<Msg>, ProgramHandle) {
+  #[cfg(feature = "ssr")]
+  {
+    server::init(update_fn, options, transfer)
+  }
+
+  #[cfg(all(feature = "hydrate", not(feature = "ssr")))]
+  {
+    client::init(update_fn, options, transfer)
+  }
+
+  #[cfg(not(any(feature = "ssr", feature = "hydrate")))]
+  {
+    let _ = transfer;
+
+    crate::init(update_fn, options)
+  }
+}
+
+#[cfg(feature = "ssr")]
+mod server {
+  use super::*;
+  use crate::{program, Waiter};
+  use futures::channel::oneshot;
+  use leptos_reactive::create_resource;
+  use std::{cell::Cell, rc::Rc};
+
+  #[track_caller]
+  pub fn init<Msg: Default + 'static>(
+    update_fn: impl Fn(Msg, Cmd<Msg>) + 'static,
+    options: Options<Msg>,
+    transfer: impl Transfer<Msg> + 'static,
+  ) -> (MsgDispatcher<Msg>, ProgramHandle) {
+    let transfer = Rc::new(transfer);
+
+    let update_fn = {
+      let transfer = transfer.clone();
+
+      move |msg: Msg, cmd| {
+        transfer.record(&msg);
+
+        update_fn(msg, cmd);
+      }
+    };
+
+    let (settled_tx, settled_rx) = oneshot::channel();
+
+    let (msg_dispatcher, handle, _) = program::start(
+      update_fn,
+      options,
+      Some(Envelope {
+        msg: Msg::default(),
+        waiter: Some(Waiter::Settled(Rc::new(settled_tx))),
+      }),
+    );
+
+    let settled_rx = Cell::new(Some(settled_rx));
+
+    create_resource(
+      || (),
+      move |_| {
+        let settled_rx = settled_rx.take();
+        let transfer = transfer.clone();
+
+        async move {
+          if let Some(settled_rx) = settled_rx {
+            let _ = settled_rx.await;
+          }
+
+          transfer.serialize()
+        }
+      },
+    );
+
+    (msg_dispatcher, handle)
+  }
+}
+
+#[cfg(all(feature = "hydrate", not(feature = "ssr")))]
+mod client {
+  use super::*;
+  use crate::program;
+  use leptos_reactive::{create_effect, create_resource, untrack, SignalGet};
+  use std::cell::Cell;
+
+  #[track_caller]
+  pub fn init<Msg: Default + 'static>(
+    update_fn: impl Fn(Msg, Cmd<Msg>) + 'static,
+    options: Options<Msg>,
+    transfer: impl Transfer<Msg> + 'static,
+  ) -> (MsgDispatcher<Msg>, ProgramHandle) {
+    let (msg_dispatcher, handle, program) =
+      program::start(update_fn, options, None);
+
+    // Queue messages until we know where to resume from
+    handle.pause();
+
+    // Only resolves to `Some(_)` while hydrating
+    let state = create_resource(|| (), |_| async { None::<String> });
+
+    let is_resumed = Cell::new(false);
+
+    create_effect(move |_| {
+      let Some(state) = state.get() else {
+        return;
+      };
+
+      if is_resumed.replace(true) {
+        return;
+      }
+
+      untrack(|| {
+        let is_restored = state.is_some_and(|state| {
+          transfer.restore(&state, &|msg| program.replay(msg))
+        });
+
+        if !is_restored {
+          program.handle(Envelope::new(Msg::default()));
+        }
+
+        handle.resume();
+      });
+    });
+
+    (msg_dispatcher, handle)
+  }
+}
+
+#[doc(hidden)]
+/// Types which can be transferred from the server to the client.
+///
+/// Only requires [`serde::Serialize`] and [`serde::Deserialize`]
+/// when either the `ssr` or `hydrate` feature is enabled.
+#[cfg(any(feature = "ssr", feature = "hydrate"))]
+pub trait Transferable: serde::Serialize + serde::de::DeserializeOwned {}
+
+#[cfg(any(feature = "ssr", feature = "hydrate"))]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> Transferable for T {}
+
+#[doc(hidden)]
+/// Types which can be transferred from the server to the client.
+///
+/// Only requires [`serde::Serialize`] and [`serde::Deserialize`]
+/// when either the `ssr` or `hydrate` feature is enabled.
+#[cfg(not(any(feature = "ssr", feature = "hydrate")))]
+pub trait Transferable {}
+
+#[cfg(not(any(feature = "ssr", feature = "hydrate")))]
+impl<T> Transferable for T {}
+
+#[cfg_attr(not(feature = "ssr"), allow(unused_variables))]
+fn to_json<T: Transferable>(value: &T) -> Option<String> {
+  #[cfg(feature = "ssr")]
+  {
+    serde_json::to_string(value)
+      .map_err(|err| tracing::warn!("failed to serialize model: {err}"))
+      .ok()
+  }
+
+  #[cfg(not(feature = "ssr"))]
+  {
+    None
+  }
+}
+
+#[cfg_attr(
+  not(all(feature = "hydrate", not(feature = "ssr"))),
+  allow(unused_variables)
+)]
+fn from_json<T: Transferable>(json: &str) -> Option<T> {
+  #[cfg(all(feature = "hydrate", not(feature = "ssr")))]
+  {
+    serde_json::from_str(json)
+      .map_err(|err| tracing::warn!("failed to deserialize model: {err}"))
+      .ok()
+  }
+
+  #[cfg(not(all(feature = "hydrate", not(feature = "ssr"))))]
+  {
+    None
+  }
+}
+
+#[doc(hidden)]
+/// How a model's state is sent from the server to the client.
+pub trait Transfer<Msg> {
+  /// Called on the server before the update function handles `msg`.
+  fn record(&self, msg: &Msg);
+
+  /// Called on the server once the initial message has settled.
+  fn serialize(&self) -> Option<String>;
+
+  /// Called on the client with the state sent by the server. Returns
+  /// `true` if the model was restored.
+  fn restore(&self, state: &str, replay: &dyn Fn(Msg)) -> bool;
+}
+
+#[doc(hidden)]
+/// Transfers a snapshot of the model.
+pub struct SnapshotTransfer<M> {
+  snapshot: Box<dyn Fn() -> M>,
+  restore: Box<dyn Fn(M)>,
+}
+
+impl<M> SnapshotTransfer<M> {
+  /// Transfers the model returned by `snapshot`, handing it to
+  /// `restore` on the client.
+  pub fn new(
+    snapshot: impl Fn() -> M + 'static,
+    restore: impl Fn(M) + 'static,
+  ) -> Self {
+    Self {
+      snapshot: Box::new(snapshot),
+      restore: Box::new(restore),
+    }
+  }
+}
+
+impl<M: Transferable, Msg> Transfer<Msg> for SnapshotTransfer<M> {
+  fn record(&self, _: &Msg) {}
+
+  fn serialize(&self) -> Option<String> {
+    to_json(&(self.snapshot)())
+  }
+
+  fn restore(&self, state: &str, _: &dyn Fn(Msg)) -> bool {
+    from_json(state).map(&self.restore).is_some()
+  }
+}
+
+#[doc(hidden)]
+/// Transfers every message handled by the model.
+pub struct MsgLogTransfer<Msg> {
+  log: RefCell<Vec<String>>,
+  _msg: PhantomData<Msg>,
+}
+
+impl<Msg> Default for MsgLogTransfer<Msg> {
+  fn default() -> Self {
+    Self {
+      log: Default::default(),
+      _msg: PhantomData,
+    }
+  }
+}
+
+impl<Msg: Transferable> Transfer<Msg> for MsgLogTransfer<Msg> {
+  fn record(&self, msg: &Msg) {
+    if let Some(msg) = to_json(msg) {
+      self.log.borrow_mut().push(msg);
+    }
+  }
+
+  fn serialize(&self) -> Option<String> {
+    Some(format!("[{}]", self.log.borrow().join(",")))
+  }
+
+  fn restore(&self, state: &str, replay: &dyn Fn(Msg)) -> bool {
+    from_json::<Vec<Msg>>(state)
+      .map(|msgs| msgs.into_iter().for_each(replay))
+      .is_some()
+  }
+}
@@ -0,0 +1,236 @@
+//! Application-wide models.
+//!
+//! Models are usually bound to the component they were initialized
+//! in. Calling `init_global()` instead registers the model as a
+//! global store, which is created the first time it's accessed with
+//! [`Store::global`], from any component or from non-component code.
+//!
+//! ```rust
+//! use leptos::*;
+//! use leptos_tea::{store::Store, Cmd};
+//!
+//! #[derive(Default, leptos_tea::Model)]
+//! struct SessionModel {
+//!   user: Option<String>,
+//! }
+//!
+//! #[derive(Default)]
+//! enum Msg {
+//!   #[default]
+//!   Init,
+//!   LogOut,
+//! }
+//!
+//! fn update(model: UpdateSessionModel, msg: Msg, _: Cmd<Msg>) {
+//!   match msg {
+//!     Msg::LogOut => model.user.set(None),
+//!     Msg::Init => {}
+//!   }
+//! }
+//!
+//! #[component]
+//! fn App() -> impl IntoView {
+//!   SessionModel::default().init_global(update);
+//!
+//!   view! { <LogOut /> }
+//! }
+//!
+//! #[component]
+//! fn LogOut() -> impl IntoView {
+//!   let (_, msg_dispatcher) = Store::<SessionModel>::global::<Msg>();
+//!
+//!   view! {
+//!     <button on:click=msg_dispatcher.on(|_| Msg::LogOut)>"Log out"</button>
+//!   }
+//! }
+//! ```
+//!
+//! The model is created under the [`Owner`] `init_global()` was called
+//! in, so it should be called at the root of the app. Stores are
+//! global to the current thread, other than with the `ssr` feature,
+//! where they are provided through context instead, so requests
+//! rendered on the same thread never share them. On the server,
+//! stores can therefore only be accessed from within components.
+
+use crate::{MsgDispatcher, SplitModel};
+use leptos_reactive::{with_owner, Owner};
+use std::{
+  any::{type_name, Any, TypeId},
+  cell::RefCell,
+  collections::HashMap,
+  marker::PhantomData,
+  panic::{self, AssertUnwindSafe},
+  rc::Rc,
+};
+
+#[derive(Clone, Default)]
+struct Stores(Rc<RefCell<HashMap<TypeId, Rc<dyn Any>>>>);
+
+#[cfg(not(feature = "ssr"))]
+thread_local! {
+  static STORES: Stores = Default::default();
+}
+
+/// Returns the stores of the current thread.
+#[cfg(not(feature = "ssr"))]
+fn stores() -> Option<Stores> {
+  Some(STORES.with(Clone::clone))
+}
+
+/// Returns the stores of the current request, if any were registered.
+#[cfg(feature = "ssr")]
+fn stores() -> Option<Stores> {
+  leptos_reactive::use_context::<Stores>()
+}
+
+/// Same as [`stores`], but provides them to the current owner if
+/// there aren't any yet.
+fn stores_or_provide() -> Stores {
+  #[cfg(not(feature = "ssr"))]
+  {
+    STORES.with(Clone::clone)
+  }
+
+  #[cfg(feature = "ssr")]
+  {
+    stores().unwrap_or_else(|| {
+      let stores = Stores::default();
+
+      leptos_reactive::provide_context(stores.clone());
+
+      stores
+    })
+  }
+}
+
+/// Accessor for the global store of the model `M`.
+pub struct Store<M>(PhantomData<M>);
+
+impl<M: SplitModel + 'static> Store<M> {
+  /// Returns the view model and message dispatcher of the global
+  /// store, initializing it if this is the first access.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `init_global()` wasn't called for `M`, or was called
+  /// with a different message type.
+  #[track_caller]
+  pub fn global<Msg: 'static>() -> (M::View, MsgDispatcher<Msg>) {
+    Self::try_global().unwrap_or_else(|| {
+      panic!(
+        "no global store of `{}` with messages of type `{}` found\n\ntry \
+         calling `{}::init_global()` at the root of the app",
+        type_name::<M>(),
+        type_name::<Msg>(),
+        type_name::<M>(),
+      )
+    })
+  }
+
+  /// Same as [`Store::global`], but returns `None` instead of
+  /// panicking.
+  pub fn try_global<Msg: 'static>() -> Option<(M::View, MsgDispatcher<Msg>)> {
+    let entry = stores()?.0.borrow().get(&TypeId::of::<M>()).cloned()?;
+
+    entry.downcast_ref::<Entry<M::View, Msg>>()?.get()
+  }
+
+  /// Returns `true` if `init_global()` was called for `M`.
+  pub fn is_registered() -> bool {
+    stores()
+      .is_some_and(|stores| stores.0.borrow().contains_key(&TypeId::of::<M>()))
+  }
+}
+
+#[doc(hidden)]
+/// Registers the global store of `M`, to be created by `init` under
+/// the current [`Owner`] once it's first accessed.
+///
+/// You shouldn't need to use this, as it will be
+/// code generated by the [`Model`](crate::Model) derive macro.
+#[track_caller]
+pub fn register<M: SplitModel + 'static, Msg: 'static>(
+  init: impl FnOnce() -> (M::View, MsgDispatcher<Msg>) + 'static,
+) {
+  let owner = Owner::current().expect(
+    "`Model::init_global()` must be called within the context of an \
+     `Owner`\n\ntry calling `Model::init_global()` at the top level of the \
+     root component",
+  );
+
+  let stores = stores_or_provide();
+  let mut stores = stores.0.borrow_mut();
+
+  // Already registered, unless the owner it was created under has
+  // since been disposed
+  let is_alive = stores
+    .get(&TypeId::of::<M>())
+    .and_then(|entry| entry.downcast_ref::<Entry<M::View, Msg>>())
+    .is_some_and(Entry::is_alive);
+
+  if !is_alive {
+    let entry = Entry::<M::View, Msg>(RefCell::new(State::Pending(
+      owner,
+      Box::new(init),
+    )));
+
+    stores.insert(TypeId::of::<M>(), Rc::new(entry));
+  }
+}
+
+struct Entry<View: 'static, Msg: 'static>(RefCell<State<View, Msg>>);
+
+enum State<View: 'static, Msg: 'static> {
+  Pending(Owner, Box<dyn FnOnce() -> (View, MsgDispatcher<Msg>)>),
+  Initializing,
+  Ready(View, MsgDispatcher<Msg>),
+  /// Initializing panicked.
+  Failed,
+}
+
+impl<View: Copy, Msg> Entry<View, Msg> {
+  #[track_caller]
+  fn get(&self) -> Option<(View, MsgDispatcher<Msg>)> {
+    let state =
+      std::mem::replace(&mut *self.0.borrow_mut(), State::Initializing);
+
+    let (view, msg_dispatcher) = match state {
+      State::Pending(owner, init) => {
+        match panic::catch_unwind(AssertUnwindSafe(|| with_owner(owner, init)))
+        {
+          Ok(ready) => ready,
+          Err(payload) => {
+            // Rather than staying `Initializing` forever, so the store
+            // can be registered again
+            *self.0.borrow_mut() = State::Failed;
+
+            panic::resume_unwind(payload);
+          }
+        }
+      }
+      State::Initializing => panic!(
+        "global store with view model `{}` was accessed while it was \
+         being initialized",
+        type_name::<View>(),
+      ),
+      State::Ready(view, msg_dispatcher) => (view, msg_dispatcher),
+      State::Failed => {
+        *self.0.borrow_mut() = State::Failed;
+
+        return None;
+      }
+    };
+
+    *self.0.borrow_mut() = State::Ready(view, msg_dispatcher);
+
+    (!msg_dispatcher.is_disposed()).then_some((view, msg_dispatcher))
+  }
+
+  fn is_alive(&self) -> bool {
+    match &*self.0.borrow() {
+      State::Ready(_, msg_dispatcher) => !msg_dispatcher.is_disposed(),
+      State::Pending(..) | State::Initializing => true,
+      State::Failed => false,
+    }
+  }
+}
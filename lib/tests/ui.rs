@@ -0,0 +1,10 @@
+//! Checks the code generated by the derive macros compiles for each
+//! option, and that invalid options are rejected with a useful error.
+
+#[test]
+fn ui() {
+  let t = trybuild::TestCases::new();
+
+  t.pass("tests/ui/pass/*.rs");
+  t.compile_fail("tests/ui/fail/*.rs");
+}
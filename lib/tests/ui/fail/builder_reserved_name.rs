@@ -0,0 +1,8 @@
+#[derive(leptos_tea::Model)]
+#[model(builder)]
+struct Model {
+  counter: usize,
+  init: bool,
+}
+
+fn main() {}
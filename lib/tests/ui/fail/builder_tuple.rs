@@ -0,0 +1,5 @@
+#[derive(leptos_tea::Model)]
+#[model(builder)]
+struct Model(usize);
+
+fn main() {}
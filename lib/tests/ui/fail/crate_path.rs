@@ -0,0 +1,14 @@
+#[derive(Default, leptos_tea::Model)]
+#[model(crate = missing::leptos_tea)]
+struct Model {
+  count: usize,
+}
+
+#[derive(Default, leptos_tea::Msg)]
+#[msg(crate = missing::leptos_tea)]
+enum Msg {
+  #[default]
+  Init,
+}
+
+fn main() {}
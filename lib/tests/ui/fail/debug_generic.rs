@@ -0,0 +1,14 @@
+#[derive(Default, Clone, Copy, leptos_tea::Model)]
+#[model(derive(Debug))]
+struct Model<T: Copy + 'static> {
+  value: T,
+}
+
+#[derive(Default, Clone, Copy)]
+struct NotDebug;
+
+fn view(model: ViewModel<NotDebug>) {
+  let _ = format!("{model:?}");
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(Default, leptos_tea::Model)]
+struct Model {
+  #[model(default = 1)]
+  step: usize,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(Default, leptos_tea::Model)]
+struct Model {
+  #[model(eq, store)]
+  value: usize,
+}
+
+fn main() {}
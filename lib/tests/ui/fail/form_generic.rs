@@ -0,0 +1,7 @@
+#[derive(Default, leptos_tea::Model)]
+#[model(form)]
+struct Model<T: 'static> {
+  value: T,
+}
+
+fn main() {}
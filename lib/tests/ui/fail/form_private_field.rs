@@ -0,0 +1,17 @@
+mod sign_up {
+  #[derive(Default, leptos_tea::Model)]
+  #[model(form)]
+  pub struct SignUpModel {
+    pub email: String,
+    token: String,
+    #[model(update_vis = pub(self))]
+    pub verified: bool,
+  }
+}
+
+use sign_up::SignUpModelFieldMsg;
+
+fn main() {
+  let _ = SignUpModelFieldMsg::Token(String::new());
+  let _ = SignUpModelFieldMsg::Verified(true);
+}
@@ -0,0 +1,6 @@
+#[derive(leptos_tea::Msg)]
+enum Msg<T> {
+  Set(T),
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(leptos_tea::Msg)]
+enum Msg {
+  #[msg(init)]
+  Init(usize),
+}
+
+fn main() {}
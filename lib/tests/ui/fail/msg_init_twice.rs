@@ -0,0 +1,9 @@
+#[derive(leptos_tea::Msg)]
+enum Msg {
+  #[msg(init)]
+  Init,
+  #[msg(init)]
+  Reset,
+}
+
+fn main() {}
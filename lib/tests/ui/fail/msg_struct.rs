@@ -0,0 +1,6 @@
+#[derive(leptos_tea::Msg)]
+struct Msg {
+  value: usize,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+#[derive(Default, leptos_tea::Model)]
+struct Inner {
+  value: usize,
+}
+
+#[derive(Default, leptos_tea::Model)]
+struct Outer {
+  #[model]
+  #[model(store)]
+  inner: Inner,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+#[derive(Default, leptos_tea::Model)]
+struct Model {
+  #[model(store)]
+  abort_handle: Option<u32>,
+}
+
+fn view(model: ViewModel) {
+  model.abort_handle.set_value(None);
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+mod model {
+  #[derive(Default, leptos_tea::Model)]
+  pub struct Model {
+    #[model(update_vis = pub(self))]
+    pub token: String,
+  }
+}
+
+fn update(model: model::UpdateModel) {
+  let _ = model.token;
+}
+
+fn main() {}
@@ -0,0 +1,41 @@
+use leptos_tea::Cmd;
+
+#[derive(Default)]
+enum Msg {
+  #[default]
+  Init,
+}
+
+// Has no `Default`, so it must be given a default
+#[derive(Clone, Copy)]
+struct Step(usize);
+
+#[derive(leptos_tea::Model)]
+#[model(builder)]
+struct CounterModel {
+  #[model(default = Step(1))]
+  step: Step,
+  counter: usize,
+  #[model(store)]
+  label: String,
+  #[model(skip)]
+  clicks: usize,
+}
+
+fn update(_: UpdateCounterModel, _: Msg, _: Cmd<Msg>) {}
+
+fn component() {
+  let model: CounterModel = CounterModel::builder().counter(5).build();
+  let _: Step = model.step;
+
+  let _: CounterModel = CounterModel::builder()
+    .step(Step(2))
+    .label("count".to_string())
+    .clicks(3)
+    .build();
+
+  let (_, _): (ViewCounterModel, _) =
+    CounterModel::builder().counter(5).init(update);
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+use leptos_tea::leptos_reactive::SignalUpdate;
+
+mod reexport {
+  pub use ::leptos_tea;
+}
+
+#[derive(Default, Clone, leptos_tea::Model)]
+#[model(crate = reexport::leptos_tea)]
+struct Model {
+  count: usize,
+  #[model(store)]
+  page: u32,
+}
+
+#[derive(Default, Clone, leptos_tea::Model)]
+#[model(crate = "reexport::leptos_tea")]
+struct QuotedModel(usize);
+
+#[derive(Default, leptos_tea::Msg)]
+#[msg(crate = reexport::leptos_tea)]
+enum Msg {
+  #[default]
+  Init,
+  Increment,
+}
+
+fn update(model: UpdateModel, msg: Msg, _: leptos_tea::Cmd<Msg>) {
+  if let Msg::Increment = msg {
+    model.count.update(|count| *count += 1);
+  }
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+#[derive(Default, Clone, leptos_tea::Model)]
+#[model(derive(Clone, Copy, Debug), attr(allow(dead_code)))]
+struct Model {
+  query: String,
+  #[model(store)]
+  page: u32,
+  #[model(skip)]
+  retries: usize,
+  #[model]
+  filter: Filter,
+}
+
+#[derive(Default, Clone, leptos_tea::Model)]
+#[model(derive(Clone, Copy, Debug))]
+struct Filter {
+  active: bool,
+}
+
+#[derive(Default, Clone, leptos_tea::Model)]
+#[model(derive(Clone, Copy, Debug))]
+struct GenericModel<T: Copy + 'static>(T, #[model(skip)] u8);
+
+fn assert_traits<T: Clone + Copy + std::fmt::Debug>(_: T) {}
+
+fn update(model: UpdateModel, generic: UpdateGenericModel<i32>) {
+  assert_traits(model);
+  assert_traits(generic);
+  let _ = format!("{model:?} {generic:?}");
+}
+
+fn view(model: ViewModel, generic: ViewGenericModel<i32>) {
+  assert_traits(model);
+  assert_traits(generic);
+  let _ = format!("{model:?} {generic:?}");
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+use leptos_tea::{leptos_reactive::*, EqSignal};
+
+#[derive(Default, leptos_tea::Model)]
+struct SearchModel {
+  #[model(eq)]
+  query: String,
+  page: usize,
+}
+
+#[derive(Default, leptos_tea::Model)]
+#[model(eq)]
+struct FilterModel {
+  tag: String,
+  #[model(store)]
+  cache: Vec<String>,
+  #[model]
+  search: SearchModel,
+}
+
+fn update(model: UpdateFilterModel) {
+  let _: EqSignal<String> = model.tag;
+  let _: StoredValue<Vec<String>> = model.cache;
+  let _: EqSignal<String> = model.search.query;
+  let _: RwSignal<usize> = model.search.page;
+
+  model.tag.set("rust".to_string());
+  model.tag.update(|tag| tag.push('!'));
+  model.tag.set_untracked(String::new());
+  model.tag.update_untracked(String::clear);
+}
+
+fn view(model: ViewFilterModel) {
+  let _: ReadSignal<String> = model.tag;
+  let _: ReadSignal<String> = model.search.query;
+}
+
+fn main() {}
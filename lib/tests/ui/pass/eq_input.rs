@@ -0,0 +1,31 @@
+use leptos_tea::{leptos_reactive::*, EqSignal};
+
+#[derive(Default)]
+enum Msg {
+  #[default]
+  Init,
+  UserIdChanged(u32),
+}
+
+#[derive(leptos_tea::Model)]
+#[model(eq)]
+struct ProfileModel {
+  #[model(input(on_change = Msg::UserIdChanged))]
+  user_id: ReadSignal<u32>,
+  name: String,
+}
+
+fn update(model: UpdateProfileModel) {
+  let _: ReadSignal<u32> = model.user_id;
+  let _: EqSignal<String> = model.name;
+}
+
+fn view(model: ViewProfileModel) {
+  let _: ReadSignal<u32> = model.user_id;
+}
+
+fn adopt(user_id: ReadSignal<u32>, name: RwSignal<String>) {
+  let _ = ProfileModel::from_signals(user_id, name);
+}
+
+fn main() {}
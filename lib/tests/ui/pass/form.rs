@@ -0,0 +1,55 @@
+mod sign_up {
+  #[derive(Default, leptos_tea::Model)]
+  #[model(form)]
+  pub struct SignUpModel {
+    pub email: String,
+    pub age: u8,
+    #[model(update_vis = pub)]
+    pub(super) name: String,
+    #[model(store)]
+    pub attempts: usize,
+    token: String,
+  }
+
+  fn apply_private(model: UpdateSignUpModel) {
+    let _ = model.token;
+  }
+}
+
+use leptos_tea::{leptos_reactive::ReadSignal, MsgDispatcher};
+use sign_up::{SignUpModelFieldMsg, UpdateSignUpModel, ViewSignUpModel};
+
+enum Msg {
+  Field(SignUpModelFieldMsg),
+}
+
+impl From<SignUpModelFieldMsg> for Msg {
+  fn from(msg: SignUpModelFieldMsg) -> Self {
+    Msg::Field(msg)
+  }
+}
+
+fn update(model: UpdateSignUpModel, msg: Msg) {
+  match msg {
+    Msg::Field(msg) => model.apply_field_msg(msg),
+  }
+}
+
+fn messages() -> [SignUpModelFieldMsg; 3] {
+  [
+    SignUpModelFieldMsg::Email("a@b.c".to_string()),
+    SignUpModelFieldMsg::Age(30),
+    SignUpModelFieldMsg::Name("Jo".to_string()),
+  ]
+}
+
+fn view(model: ViewSignUpModel, msg_dispatcher: MsgDispatcher<Msg>) {
+  let (_, set_email): (ReadSignal<String>, _) =
+    model.bind_email(msg_dispatcher);
+  set_email("a@b.c".to_string());
+
+  let (_, set_age): (ReadSignal<u8>, _) = model.bind_age(msg_dispatcher);
+  set_age(30);
+}
+
+fn main() {}
@@ -0,0 +1,39 @@
+use leptos_tea::MsgDispatcher;
+
+#[derive(leptos_tea::Msg)]
+pub enum Msg {
+  #[msg(init)]
+  Init,
+  /// Sets the filter.
+  SetFilter(String),
+  HTTPError {
+    code: u16,
+  },
+  Move(i32, i32),
+  Type,
+  Dispatch,
+}
+
+#[derive(leptos_tea::Msg)]
+enum Never {}
+
+fn dispatch(msg_dispatcher: MsgDispatcher<Msg>) {
+  let msg: Msg = Msg::default();
+  let _: &'static str = msg.variant_name();
+
+  msg_dispatcher.init();
+  msg_dispatcher.set_filter("active".to_string());
+  msg_dispatcher.http_error(404);
+  msg_dispatcher.r#move(1, -1);
+  msg_dispatcher.r#type();
+
+  // Collides with `MsgDispatcher::dispatch`
+  DispatchMsg::dispatch(&msg_dispatcher);
+  msg_dispatcher.dispatch(Msg::Dispatch);
+}
+
+fn never(msg: Never) -> &'static str {
+  msg.variant_name()
+}
+
+fn main() {}
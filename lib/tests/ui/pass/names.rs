@@ -0,0 +1,27 @@
+mod model {
+  #[derive(Default, Clone, leptos_tea::Model)]
+  #[model(update = "Updater", view = "Viewer")]
+  pub struct Model {
+    pub query: String,
+    #[model(update_vis = pub(self))]
+    pub token: String,
+    #[model(view_vis = pub)]
+    pub(crate) page: u32,
+  }
+}
+
+use leptos_tea::leptos_reactive::*;
+use model::{Updater, Viewer};
+
+fn update(model: Updater) {
+  let _: RwSignal<String> = model.query;
+  let _: RwSignal<u32> = model.page;
+}
+
+fn view(model: Viewer) {
+  let _: ReadSignal<String> = model.query;
+  let _: ReadSignal<String> = model.token;
+  let _: ReadSignal<u32> = model.page;
+}
+
+fn main() {}
@@ -0,0 +1,35 @@
+use leptos_tea::{leptos_reactive::*, ReadStoredValue};
+
+#[derive(Default, Clone, leptos_tea::Model)]
+struct Model {
+  query: String,
+  #[model(store)]
+  abort_handle: Option<u32>,
+  #[model(skip)]
+  retries: usize,
+}
+
+#[derive(Default, Clone, leptos_tea::Model)]
+struct TupleModel(#[model(skip)] usize, String, #[model(store)] u8);
+
+fn update(model: UpdateModel) {
+  let _: StoredValue<Option<u32>> = model.abort_handle;
+  let _: RwSignal<usize> = model.retries;
+
+  model.abort_handle.set_value(Some(1));
+  model.restore(Model::default());
+}
+
+fn view(model: ViewModel, tuple: ViewTupleModel) {
+  let _: ReadSignal<String> = model.query;
+  let _: ReadStoredValue<Option<u32>> = model.abort_handle;
+  let _: Option<u32> = model.abort_handle.get_value();
+  let _: Model = model.snapshot();
+
+  // Skipped fields aren't in the view model, so indices shift
+  let _: ReadSignal<String> = tuple.0;
+  let _: ReadStoredValue<u8> = tuple.1;
+  let _: TupleModel = tuple.snapshot();
+}
+
+fn main() {}
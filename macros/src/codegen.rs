@@ -1,74 +1,8 @@
-use crate::model::{Field, Model};
+use crate::model::{Field, Input, Model, ModelOptions, Persist, Ssr};
 use core::fmt;
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
-use syn::parse_quote;
-
-trait FieldSliceExt: AsRef<[Field]> {
-  fn to_update_model_fields(&self, is_named: bool) -> TokenStream {
-    let this = self.as_ref();
-
-    let fields = this.iter().map(
-      |Field {
-         vis,
-         name,
-         ty,
-         is_nested_model,
-       }| {
-        let ty = if *is_nested_model {
-          format_ty("Update", ty)
-        } else {
-          syn::parse_quote! { ::leptos_tea::leptos_reactive::RwSignal<#ty> }
-        };
-
-        if is_named {
-          quote! { #vis #name: #ty }
-        } else {
-          quote! { #vis #ty }
-        }
-      },
-    );
-
-    if is_named {
-      quote! { { #( #fields ),* } }
-    } else {
-      quote! { ( #( #fields ),* ); }
-    }
-  }
-
-  fn to_view_model_fields(&self, is_named: bool) -> TokenStream {
-    let this = self.as_ref();
-
-    let fields = this.iter().map(
-      |Field {
-         vis,
-         name,
-         ty,
-         is_nested_model,
-       }| {
-        let ty = if *is_nested_model {
-          format_ty("View", ty)
-        } else {
-          syn::parse_quote! { ::leptos_tea::leptos_reactive::ReadSignal<#ty> }
-        };
-
-        if is_named {
-          quote! { #vis #name: #ty }
-        } else {
-          quote! { #vis #ty }
-        }
-      },
-    );
-
-    if is_named {
-      quote! { { #( #fields ),* } }
-    } else {
-      quote! { ( #( #fields ),* ); }
-    }
-  }
-}
-
-impl<T> FieldSliceExt for T where T: AsRef<[Field]> {}
+use syn::{ext::IdentExt, parse_quote};
 
 pub fn codegen(
   Model {
@@ -77,9 +11,10 @@ pub fn codegen(
     generics,
     is_named,
     fields,
+    options,
   }: Model,
 ) -> TokenStream {
-  codegen_struct(vis, name, generics, is_named, fields)
+  codegen_struct(vis, name, generics, is_named, fields, options)
 }
 
 fn codegen_struct(
@@ -88,6 +23,7 @@ fn codegen_struct(
   generics: syn::Generics,
   is_named: bool,
   fields: Vec<Field>,
+  options: ModelOptions,
 ) -> TokenStream {
   let update_struct = generate_model_struct(
     ModelStructKind::Update,
@@ -96,6 +32,7 @@ fn codegen_struct(
     &generics,
     is_named,
     &fields,
+    &options,
   );
 
   let view_struct = generate_model_struct(
@@ -105,135 +42,1131 @@ fn codegen_struct(
     &generics,
     is_named,
     &fields,
+    &options,
   );
 
-  let model_impl =
-    generate_model_impl(&vis, &name, &generics, is_named, &fields);
+  let model_impl =
+    generate_model_impl(&vis, &name, &generics, is_named, &fields, &options);
+
+  let update_model_impl = generate_update_model_impl(
+    &vis, &name, &generics, is_named, &fields, &options,
+  );
+
+  let view_model_impl = generate_view_model_impl(
+    &vis, &name, &generics, is_named, &fields, &options,
+  );
+
+  let validation_impl =
+    generate_validation_impl(&vis, &name, &generics, &fields, &options);
+
+  let form_impl = options
+    .form
+    .then(|| generate_form_impl(&vis, &name, &generics, &fields, &options));
+
+  quote! {
+    #update_struct
+
+    #view_struct
+
+    #model_impl
+
+    #update_model_impl
+
+    #view_model_impl
+
+    #validation_impl
+
+    #form_impl
+  }
+}
+
+/// Generates `{name}FieldMsg`, with a variant setting each field held
+/// in a signal, along with `UpdateX::apply_field_msg` to handle it and
+/// a `bind_{field}` method on the view model for each field.
+///
+/// Fields of the update model which are less visible than the model
+/// are left out, since the message could otherwise set them from
+/// anywhere.
+fn generate_form_impl(
+  vis: &syn::Visibility,
+  name: &syn::Ident,
+  generics: &syn::Generics,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> TokenStream {
+  let krate = options.krate();
+
+  let update_model_name = options.update_name(name);
+  let view_model_name = options.view_name(name);
+  let field_msg_name = format_ident!("{name}FieldMsg");
+
+  let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+  let form_fields = fields
+    .iter()
+    .filter(|field| {
+      !field.is_nested_model
+        && !field.is_stored
+        && !field.is_skipped
+        && field.input.is_none()
+        && is_visible_at(field.update_vis.as_ref().unwrap_or(&field.vis), vis)
+    })
+    .map(|field| {
+      let name = field.name.as_ref().unwrap();
+      let variant = format_ident!(
+        "{}",
+        to_pascal_case(&name.unraw().to_string()),
+        span = name.span()
+      );
+
+      (field, name, variant)
+    })
+    .collect::<Vec<_>>();
+
+  let variants = form_fields.iter().map(|(field, _, variant)| {
+    let ty = &field.ty;
+
+    quote! { #variant(#ty) }
+  });
+
+  let apply_arms = form_fields.iter().map(|(_, name, variant)| {
+    quote! {
+      #field_msg_name::#variant(value) => {
+        #krate::leptos_reactive::SignalSet::set(&self.#name, value);
+      }
+    }
+  });
+
+  let bind_fns = form_fields.iter().map(|(field, name, variant)| {
+    let vis = field.view_vis.as_ref().unwrap_or(&field.vis);
+    let ty = &field.ty;
+    let bind_name = format_ident!("bind_{}", name.unraw());
+
+    quote! {
+      /// Returns the value of the field, along with a setter which
+      /// dispatches
+      #[doc = ::core::concat!(
+        "[`",
+        ::core::stringify!(#field_msg_name),
+        "::",
+        ::core::stringify!(#variant),
+        "`].",
+      )]
+      #vis fn #bind_name<__Msg>(
+        &self,
+        msg_dispatcher: #krate::MsgDispatcher<__Msg>,
+      ) -> (
+        #krate::leptos_reactive::ReadSignal<#ty>,
+        impl ::core::ops::Fn(#ty) + ::core::marker::Copy + 'static,
+      )
+      where
+        __Msg: ::core::convert::From<#field_msg_name> + 'static,
+      {
+        (self.#name, move |value| {
+          msg_dispatcher.dispatch(::core::convert::From::from(
+            #field_msg_name::#variant(value),
+          ))
+        })
+      }
+    }
+  });
+
+  // Uninhabited enums can't be matched on by reference
+  let apply_body = if form_fields.is_empty() {
+    quote! { match msg {} }
+  } else {
+    quote! {
+      match msg {
+        #( #apply_arms )*
+      }
+    }
+  };
+
+  quote! {
+    /// Sets a single field of
+    #[doc = ::core::concat!("[`", ::core::stringify!(#name), "`].")]
+    #vis enum #field_msg_name {
+      #( #variants ),*
+    }
+
+    impl #impl_generics #update_model_name #type_generics #where_clause {
+      /// Sets the field `msg` is for.
+      #vis fn apply_field_msg(&self, msg: #field_msg_name) {
+        #apply_body
+      }
+    }
+
+    impl #impl_generics #view_model_name #type_generics #where_clause {
+      #( #bind_fns )*
+    }
+  }
+}
+
+/// Whether something with `field_vis` can be reached from everywhere
+/// `vis` can. `pub(super)` and `pub(in path)` can't be compared with
+/// each other without resolving the paths, so are only visible at the
+/// exact same visibility.
+fn is_visible_at(field_vis: &syn::Visibility, vis: &syn::Visibility) -> bool {
+  fn rank(vis: &syn::Visibility) -> u8 {
+    match vis {
+      syn::Visibility::Public(_) => 3,
+      syn::Visibility::Restricted(restricted) => {
+        if restricted.path.is_ident("crate") {
+          2
+        } else if restricted.path.is_ident("self") {
+          0
+        } else {
+          1
+        }
+      }
+      syn::Visibility::Inherited => 0,
+    }
+  }
+
+  let (field_rank, rank) = (rank(field_vis), rank(vis));
+
+  field_rank > rank
+    || (field_rank == rank
+      && (rank != 1
+        || quote! { #field_vis }.to_string() == quote! { #vis }.to_string()))
+}
+
+/// `first_name` -> `FirstName`
+fn to_pascal_case(name: &str) -> String {
+  name
+    .split('_')
+    .map(|word| {
+      let mut chars = word.chars();
+
+      chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default()
+    })
+    .collect()
+}
+
+/// Whether the update and view models hold the memos created by
+/// [`generate_validation_impl`].
+fn has_validation(fields: &[Field], options: &ModelOptions) -> bool {
+  options.validate.is_some()
+    || fields.iter().any(|field| field.validate.is_some())
+}
+
+/// Generates `{name}Errors`, holding a memo with the error of each
+/// field annotated with `validate = ...`, as well as `error()` for the
+/// struct-level validator and `is_valid()` on the view model.
+fn generate_validation_impl(
+  vis: &syn::Visibility,
+  name: &syn::Ident,
+  generics: &syn::Generics,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> Option<TokenStream> {
+  if !has_validation(fields, options) {
+    return None;
+  }
+
+  let krate = options.krate();
+
+  let validated_fields = fields
+    .iter()
+    .filter_map(|field| Some((field, field.validate.as_ref()?)))
+    .collect::<Vec<_>>();
+
+  let view_model_name = options.view_name(name);
+  let errors_name = format_ident!("{name}Errors");
+  let validation_name = format_ident!("{name}Validation");
+
+  let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+  let error_ty = quote! {
+    #krate::leptos_reactive::Memo<
+      ::core::option::Option<::std::string::String>,
+    >
+  };
+
+  let errors = (!validated_fields.is_empty()).then(|| {
+    let (errors_fields, init_errors_fields): (Vec<_>, Vec<_>) =
+      validated_fields
+        .iter()
+        .map(|(field, validate)| {
+          let vis = field.view_vis.as_ref().unwrap_or(&field.vis);
+          let name = field.name.as_ref().unwrap();
+
+          (
+            quote! { #vis #name: #error_ty },
+            quote! {
+              #name: #krate::leptos_reactive::create_memo(move |_| {
+                #krate::leptos_reactive::SignalWith::with(
+                  &__view_model.#name,
+                  |value| (#validate)(value),
+                )
+                .err()
+              })
+            },
+          )
+        })
+        .unzip();
+
+    let field_names = validated_fields
+      .iter()
+      .map(|(field, _)| field.name.as_ref().unwrap());
+
+    let struct_def = quote! {
+      /// Errors of the validated fields of
+      #[doc = ::core::concat!("[`", ::core::stringify!(#name), "`],")]
+      /// which are `None` while the fields are valid.
+      #[derive(Clone, Copy)]
+      #vis struct #errors_name {
+        #( #errors_fields ),*
+      }
+    };
+
+    let init = quote! {
+      let __errors = #errors_name {
+        #( #init_errors_fields ),*
+      };
+    };
+
+    let checks = quote! {
+      #(
+        #krate::leptos_reactive::SignalWith::with(
+          &__errors.#field_names,
+          ::core::option::Option::is_none,
+        )
+      )&&*
+    };
+
+    let accessor = quote! {
+      /// Returns the memos with the error of each validated field.
+      #vis fn errors(&self) -> #errors_name {
+        self.__validation().errors
+      }
+    };
+
+    (struct_def, init, checks, accessor)
+  });
+
+  let error = options.validate.as_ref().map(|validate| {
+    let init = quote! {
+      let __error = #krate::leptos_reactive::create_memo(move |_| {
+        (#validate)(&__view_model).err()
+      });
+    };
+
+    let check = quote! {
+      #krate::leptos_reactive::SignalWith::with(
+        &__error,
+        ::core::option::Option::is_none,
+      )
+    };
+
+    let accessor = quote! {
+      /// Returns the memo with the error of the model as a whole.
+      #vis fn error(&self) -> #error_ty {
+        self.__validation().error
+      }
+    };
+
+    (init, check, accessor)
+  });
+
+  let errors_struct = errors.as_ref().map(|(struct_def, ..)| struct_def);
+  let init_errors = errors.as_ref().map(|(_, init, ..)| init);
+  let errors_accessor = errors.as_ref().map(|(.., accessor)| accessor);
+  let errors_field = errors.as_ref().map(|_| quote! { errors: #errors_name, });
+  let errors_value = errors.as_ref().map(|_| quote! { errors: __errors, });
+
+  let init_error = error.as_ref().map(|(init, ..)| init);
+  let error_accessor = error.as_ref().map(|(.., accessor)| accessor);
+  let error_field = error.as_ref().map(|_| quote! { error: #error_ty, });
+  let error_value = error.as_ref().map(|_| quote! { error: __error, });
+
+  let checks = errors
+    .iter()
+    .map(|(_, _, checks, _)| checks)
+    .chain(error.iter().map(|(_, check, _)| check));
+
+  Some(quote! {
+    #errors_struct
+
+    /// Memos created once when the model is split, rather than on
+    /// every call to the accessors of the view model.
+    #[derive(Clone, Copy)]
+    struct #validation_name {
+      #errors_field
+      #error_field
+      is_valid: #krate::leptos_reactive::Memo<bool>,
+    }
+
+    impl #impl_generics #view_model_name #type_generics #where_clause {
+      /// Creates the memos, which need the view model to read from.
+      fn __init_validation(&self) {
+        let __view_model = *self;
+
+        #init_errors
+        #init_error
+
+        let __is_valid = #krate::leptos_reactive::create_memo(move |_| {
+          #( #checks )&&*
+        });
+
+        self.__validation.set_value(::core::option::Option::Some(
+          #validation_name {
+            #errors_value
+            #error_value
+            is_valid: __is_valid,
+          },
+        ));
+      }
+
+      fn __validation(&self) -> #validation_name {
+        self.__validation.get_value().unwrap()
+      }
+
+      #errors_accessor
+
+      #error_accessor
+
+      /// Returns the memo which is `true` while every validator passes.
+      #vis fn is_valid(&self) -> #krate::leptos_reactive::Memo<bool> {
+        self.__validation().is_valid
+      }
+    }
+  })
+}
+
+impl Field {
+  /// The type of this field in the update model.
+  fn update_ty(&self, krate: &syn::Path) -> syn::Type {
+    let ty = &self.ty;
+
+    if self.input.is_some() {
+      ty.clone()
+    } else if self.is_nested_model {
+      parse_quote! { <#ty as #krate::SplitModel>::Update }
+    } else if self.is_stored {
+      parse_quote! { #krate::leptos_reactive::StoredValue<#ty> }
+    } else if self.is_eq {
+      parse_quote! { #krate::EqSignal<#ty> }
+    } else {
+      parse_quote! { #krate::leptos_reactive::RwSignal<#ty> }
+    }
+  }
+
+  /// The type of this field in the view model, if it isn't skipped.
+  fn view_ty(&self, krate: &syn::Path) -> Option<syn::Type> {
+    let ty = &self.ty;
+
+    if self.is_skipped {
+      None
+    } else if self.input.is_some() {
+      Some(ty.clone())
+    } else if self.is_nested_model {
+      Some(parse_quote! { <#ty as #krate::SplitModel>::View })
+    } else if self.is_stored {
+      Some(parse_quote! { #krate::ReadStoredValue<#ty> })
+    } else {
+      Some(parse_quote! { #krate::leptos_reactive::ReadSignal<#ty> })
+    }
+  }
+}
+
+enum ModelStructKind {
+  Update,
+  View,
+}
+
+impl fmt::Display for ModelStructKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Update => f.write_str("Update"),
+      Self::View => f.write_str("View"),
+    }
+  }
+}
+
+fn generate_model_struct(
+  kind: ModelStructKind,
+  vis: &syn::Visibility,
+  name: &syn::Ident,
+  generics: &syn::Generics,
+  is_named: bool,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> TokenStream {
+  let krate = options.krate();
+
+  let model_name = match kind {
+    ModelStructKind::Update => options.update_name(name),
+    ModelStructKind::View => options.view_name(name),
+  };
+
+  // `Clone` and `Copy` are always derived, and `Debug` is implemented
+  // below to print values rather than signal ids
+  let derives = options
+    .derives
+    .iter()
+    .filter(|path| {
+      !["Clone", "Copy", "Debug"]
+        .iter()
+        .any(|derive| path.is_ident(derive))
+    })
+    .collect::<Vec<_>>();
+
+  let attrs = &options.attrs;
+
+  let debug_impl = options
+    .derives
+    .iter()
+    .any(|path| path.is_ident("Debug"))
+    .then(|| {
+      generate_debug_impl(
+        &krate,
+        &kind,
+        &model_name,
+        generics,
+        is_named,
+        fields,
+      )
+    });
+
+  let model_fields = fields.iter().filter_map(|field| {
+    let Field { vis, name, .. } = field;
+
+    let (vis, ty) = match kind {
+      ModelStructKind::Update => (
+        field.update_vis.as_ref().unwrap_or(vis),
+        field.update_ty(&krate),
+      ),
+      ModelStructKind::View => (
+        field.view_vis.as_ref().unwrap_or(vis),
+        field.view_ty(&krate)?,
+      ),
+    };
+
+    if is_named {
+      Some(quote! { #vis #name: #ty })
+    } else {
+      Some(quote! { #vis #ty })
+    }
+  });
+
+  let validation_field = has_validation(fields, options).then(|| {
+    let validation_name = format_ident!("{name}Validation");
+
+    quote! {
+      __validation: #krate::leptos_reactive::StoredValue<
+        ::core::option::Option<#validation_name>,
+      >,
+    }
+  });
+
+  // Declared with the same bounds as the model, which type generics
+  // would leave out
+  let (_, _, where_clause) = generics.split_for_impl();
+
+  let model_fields = if is_named {
+    quote! { #where_clause { #( #model_fields, )* #validation_field } }
+  } else {
+    quote! { ( #( #model_fields ),* ) #where_clause ; }
+  };
+
+  quote! {
+    #[derive(Clone, Copy #( , #derives )*)]
+    #( #[#attrs] )*
+    #vis struct #model_name #generics #model_fields
+
+    #debug_impl
+  }
+}
+
+fn generate_debug_impl(
+  krate: &syn::Path,
+  kind: &ModelStructKind,
+  model_name: &syn::Ident,
+  generics: &syn::Generics,
+  is_named: bool,
+  fields: &[Field],
+) -> TokenStream {
+  let (impl_generics, type_generics, _) = generics.split_for_impl();
+
+  // Values are formatted, so every type parameter needs to be `Debug`,
+  // like with `#[derive(Debug)]`
+  let mut debug_generics = generics.clone();
+  let debug_where_clause = debug_generics.make_where_clause();
+
+  for param in generics.type_params() {
+    let ident = &param.ident;
+
+    debug_where_clause
+      .predicates
+      .push(parse_quote! { #ident: ::core::fmt::Debug });
+  }
+
+  let (_, _, where_clause) = debug_generics.split_for_impl();
+
+  let fields = fields
+    .iter()
+    .filter(|field| {
+      matches!(kind, ModelStructKind::Update) || !field.is_skipped
+    })
+    .enumerate()
+    .map(|(i, field)| {
+      let member = if let Some(name) = &field.name {
+        syn::Member::Named(name.clone())
+      } else {
+        syn::Member::Unnamed(i.into())
+      };
+
+      let value = if field.is_nested_model {
+        quote! { &self.#member }
+      } else if field.is_stored {
+        quote! { &#krate::debug_stored_value(self.#member) }
+      } else {
+        quote! { &#krate::debug_signal(&self.#member) }
+      };
+
+      if let Some(name) = &field.name {
+        quote! { .field(::core::stringify!(#name), #value) }
+      } else {
+        quote! { .field(#value) }
+      }
+    });
+
+  let debug = if is_named {
+    quote! { debug_struct }
+  } else {
+    quote! { debug_tuple }
+  };
+
+  quote! {
+    impl #impl_generics ::core::fmt::Debug for #model_name #type_generics
+    #where_clause
+    {
+      fn fmt(
+        &self,
+        f: &mut ::core::fmt::Formatter<'_>,
+      ) -> ::core::fmt::Result {
+        f.#debug(::core::stringify!(#model_name))
+          #( #fields )*
+          .finish()
+      }
+    }
+  }
+}
+
+fn generate_update_model_impl(
+  vis: &syn::Visibility,
+  name: &syn::Ident,
+  generics: &syn::Generics,
+  is_named: bool,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> TokenStream {
+  let krate = options.krate();
+
+  let update_model_name = options.update_name(name);
+
+  let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+  let field_names = fields
+    .iter()
+    .enumerate()
+    .map(|(i, field)| {
+      if let Some(name) = &field.name {
+        name.clone()
+      } else {
+        format_ident!("field_{i}")
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let get_fields = if is_named {
+    quote! { { #( #field_names ),* } }
+  } else {
+    quote! { ( #( #field_names ),* ) }
+  };
+
+  let restore_fields = fields.iter().zip(field_names.iter()).enumerate().map(
+    |(
+      i,
+      (
+        Field {
+          is_nested_model,
+          is_stored,
+          is_skipped,
+          input,
+          ..
+        },
+        field_name,
+      ),
+    )| {
+      let member = if is_named {
+        syn::Member::Named(field_name.clone())
+      } else {
+        syn::Member::Unnamed(i.into())
+      };
+
+      // Inputs are owned by whoever passed them in, and skipped
+      // fields can't be snapshotted, so they would be clobbered
+      // with their default
+      if input.is_some() || *is_skipped {
+        quote! { let _ = #field_name; }
+      } else if *is_nested_model {
+        quote! { self.#member.restore(#field_name); }
+      } else if *is_stored {
+        quote! { self.#member.set_value(#field_name); }
+      } else {
+        quote! {
+          #krate::leptos_reactive::SignalSet::set(
+            &self.#member,
+            #field_name,
+          );
+        }
+      }
+    },
+  );
+
+  let view_model_name = options.view_name(name);
+
+  let (view_field_names, view_fields): (Vec<_>, Vec<_>) = fields
+    .iter()
+    .zip(field_names.iter())
+    .enumerate()
+    .filter(|(_, (field, _))| !field.is_skipped)
+    .map(|(i, (field, field_name))| {
+      let member = if is_named {
+        syn::Member::Named(field_name.clone())
+      } else {
+        syn::Member::Unnamed(i.into())
+      };
+
+      let view_field = if field.is_nested_model {
+        quote! { self.#member.view() }
+      } else if field.is_stored {
+        quote! { #krate::ReadStoredValue::from(self.#member) }
+      } else if field.input.is_some() {
+        quote! { self.#member }
+      } else {
+        quote! { self.#member.read_only() }
+      };
+
+      (field_name, view_field)
+    })
+    .unzip();
+
+  let validation_field = has_validation(fields, options)
+    .then(|| quote! { __validation: self.__validation, });
+
+  let init_view_model_fields = if is_named {
+    quote! {
+      { #( #view_field_names: #view_fields, )* #validation_field }
+    }
+  } else {
+    quote! { ( #( #view_fields ),* ) }
+  };
+
+  quote! {
+    impl #impl_generics #update_model_name #type_generics #where_clause {
+      /// Sets every field to the value it has in `model`, other than
+      /// skipped fields and inputs.
+      #vis fn restore(&self, model: #name #type_generics) {
+        let #name #get_fields = model;
+
+        #( #restore_fields )*
+      }
+
+      /// Returns the view model reading from the same signals.
+      #vis fn view(&self) -> #view_model_name #type_generics {
+        #view_model_name #init_view_model_fields
+      }
+    }
+  }
+}
+
+fn generate_view_model_impl(
+  vis: &syn::Visibility,
+  name: &syn::Ident,
+  generics: &syn::Generics,
+  is_named: bool,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> TokenStream {
+  let krate = options.krate();
+
+  let view_model_name = options.view_name(name);
+
+  let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+  // Bounds are higher-ranked so that they are only checked when
+  // `snapshot()` is actually used, rather than failing to compile
+  // for models with fields which aren't `Clone`
+  let mut snapshot_generics = generics.clone();
+  let snapshot_where_clause = snapshot_generics.make_where_clause();
+
+  // Skipped fields aren't in the view model, so tuple fields
+  // after them are at a different index
+  let mut view_index = 0;
+
+  let snapshot_fields = fields
+    .iter()
+    .map(
+      |Field {
+         name,
+         ty,
+         is_nested_model,
+         is_stored,
+         is_skipped,
+         input,
+         ..
+       }| {
+        let member = if let Some(name) = name {
+          syn::Member::Named(name.clone())
+        } else {
+          syn::Member::Unnamed(view_index.into())
+        };
+
+        if !is_skipped {
+          view_index += 1;
+        }
 
-  quote! {
-    #update_struct
+        let snapshot = if *is_skipped {
+          snapshot_where_clause
+            .predicates
+            .push(parse_quote! { for<'__a> #ty: ::core::default::Default });
 
-    #view_struct
+          quote! { ::core::default::Default::default() }
+        } else if *is_nested_model {
+          snapshot_where_clause.predicates.push(parse_quote! {
+            for<'__a> <#ty as #krate::SplitModel>::View:
+              #krate::Snapshot<Model = #ty>
+          });
 
-    #model_impl
-  }
-}
+          quote! { #krate::Snapshot::snapshot(&self.#member) }
+        } else if input.is_some() {
+          snapshot_where_clause
+            .predicates
+            .push(parse_quote! { for<'__a> #ty: ::core::clone::Clone });
 
-fn format_ty(name: &str, ty: &syn::Type) -> syn::Type {
-  let mut ty = ty.clone();
+          quote! { ::core::clone::Clone::clone(&self.#member) }
+        } else if *is_stored {
+          snapshot_where_clause
+            .predicates
+            .push(parse_quote! { for<'__a> #ty: ::core::clone::Clone });
 
-  if let syn::Type::Path(syn::TypePath { path, .. }) = &mut ty {
-    let last_segment = path.segments.iter_mut().last().unwrap();
+          quote! { self.#member.get_value() }
+        } else {
+          snapshot_where_clause
+            .predicates
+            .push(parse_quote! { for<'__a> #ty: ::core::clone::Clone });
+
+          quote! {
+            #krate::leptos_reactive::SignalGet::get(&self.#member)
+          }
+        };
+
+        if is_named {
+          quote! { #member: #snapshot }
+        } else {
+          snapshot
+        }
+      },
+    )
+    .collect::<Vec<_>>();
 
-    last_segment.ident = format_ident!("{name}{}", last_segment.ident);
+  let snapshot = if is_named {
+    quote! { #name { #( #snapshot_fields ),* } }
   } else {
-    abort!(ty, "only path types are allowed")
-  }
+    quote! { #name ( #( #snapshot_fields ),* ) }
+  };
 
-  ty
-}
+  let (_, _, snapshot_where_clause) = snapshot_generics.split_for_impl();
 
-enum ModelStructKind {
-  Update,
-  View,
-}
+  quote! {
+    impl #impl_generics #krate::Snapshot
+      for #view_model_name #type_generics
+    #snapshot_where_clause
+    {
+      type Model = #name #type_generics;
 
-impl fmt::Display for ModelStructKind {
-  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    match self {
-      Self::Update => f.write_str("Update"),
-      Self::View => f.write_str("View"),
+      fn snapshot(&self) -> Self::Model {
+        #snapshot
+      }
+    }
+
+    impl #impl_generics #view_model_name #type_generics #where_clause {
+      #vis fn snapshot(&self) -> #name #type_generics
+      where
+        for<'__a> Self: #krate::Snapshot<Model = #name #type_generics>,
+      {
+        #krate::Snapshot::snapshot(self)
+      }
+
+      #vis fn changes(
+        &self,
+      ) -> impl #krate::futures::Stream<Item = #name #type_generics>
+      where
+        for<'__a> Self: #krate::Snapshot<Model = #name #type_generics>
+          + ::core::clone::Clone
+          + 'static,
+      {
+        #krate::changes(::core::clone::Clone::clone(self))
+      }
     }
   }
 }
 
-fn generate_model_struct(
-  kind: ModelStructKind,
+fn generate_model_impl(
   vis: &syn::Visibility,
   name: &syn::Ident,
   generics: &syn::Generics,
   is_named: bool,
   fields: &[Field],
+  options: &ModelOptions,
 ) -> TokenStream {
-  let model_name = format_ident!("{kind}{name}");
-
-  let model_fields = fields.iter().map(
-    |Field {
-       vis,
-       name,
-       ty,
-       is_nested_model,
-     }| {
-      let ty = match kind {
-        ModelStructKind::Update => {
-          if *is_nested_model {
-            format_ty(&kind.to_string(), ty)
-          } else {
-            parse_quote! { ::leptos_tea::leptos_reactive::RwSignal<#ty> }
-          }
-        }
-        ModelStructKind::View => {
-          if *is_nested_model {
-            format_ty(&kind.to_string(), ty)
-          } else {
-            parse_quote! { ::leptos_tea::leptos_reactive::ReadSignal<#ty> }
-          }
-        }
-      };
+  let krate = options.krate();
 
-      if is_named {
-        quote! { #vis #name: #ty }
-      } else {
-        quote! { #vis #ty }
-      }
-    },
+  let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+  let split_fn_impl =
+    generate_split_fn_impl(vis, name, generics, is_named, fields, options);
+  let from_signals_fn_impl = generate_from_signals_fn_impl(
+    vis, name, generics, is_named, fields, options,
   );
+  let (init_fn_impl, update_model_init_fn_impl, builder_init_fn_impl) =
+    generate_init_fn_impl(vis, name, generics, is_named, fields, options);
 
-  let (_, type_generics, where_clause) = generics.split_for_impl();
+  let builder = options.builder.then(|| {
+    generate_builder(vis, name, generics, fields, builder_init_fn_impl)
+  });
 
-  let model_fields = if is_named {
-    quote! { #where_clause { #( #model_fields ),* } }
-  } else {
-    quote! { ( #( #model_fields ),* ) #where_clause ; }
-  };
+  let update_model_name = options.update_name(name);
+  let view_model_name = options.view_name(name);
 
   quote! {
-    #[derive(Clone, Copy)]
-    #vis struct #model_name #type_generics #model_fields
+    impl #impl_generics #name #type_generics #where_clause {
+      #split_fn_impl
+
+      #from_signals_fn_impl
+
+      #init_fn_impl
+    }
+
+    impl #impl_generics #update_model_name #type_generics #where_clause {
+      #update_model_init_fn_impl
+    }
+
+    impl #impl_generics #krate::SplitModel for #name #type_generics
+    #where_clause
+    {
+      type Update = #update_model_name #type_generics;
+      type View = #view_model_name #type_generics;
+    }
+
+    #builder
   }
 }
 
-fn generate_model_impl(
+/// Generates `{name}Builder`, which sets fields one at a time and
+/// falls back to their `default = ...`, or `Default::default()`.
+fn generate_builder(
   vis: &syn::Visibility,
   name: &syn::Ident,
   generics: &syn::Generics,
-  is_named: bool,
   fields: &[Field],
+  init_fn_impl: TokenStream,
 ) -> TokenStream {
+  let builder_name = format_ident!("{name}Builder");
+
   let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
 
-  let split_fn_impl =
-    generate_split_fn_impl(vis, name, generics, is_named, fields);
-  let init_fn_impl = generate_init_fn_impl(vis, name, generics);
+  let mut build_generics = generics.clone();
+  build_generics
+    .make_where_clause()
+    .predicates
+    .extend(builder_default_bounds(fields));
+
+  let field_names = fields
+    .iter()
+    .map(|field| field.name.as_ref().unwrap())
+    .collect::<Vec<_>>();
+  let field_tys = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+
+  let build_fields = fields
+    .iter()
+    .map(|Field { name, default, .. }| {
+      if let Some(default) = default {
+        quote! { #name: self.#name.unwrap_or_else(|| #default) }
+      } else {
+        quote! { #name: self.#name.unwrap_or_default() }
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let (_, _, build_where_clause) = build_generics.split_for_impl();
 
   quote! {
+    /// Builder for
+    #[doc = ::core::concat!("[`", ::core::stringify!(#name), "`],")]
+    /// created with
+    #[doc = ::core::concat!("[`", ::core::stringify!(#name), "::builder`].")]
+    #[must_use]
+    #vis struct #builder_name #generics #where_clause {
+      #( #field_names: ::core::option::Option<#field_tys> ),*
+    }
+
     impl #impl_generics #name #type_generics #where_clause {
-      #split_fn_impl
+      /// Returns a builder for the model, whose unset fields take
+      /// their default value.
+      #vis fn builder() -> #builder_name #type_generics {
+        #builder_name {
+          #( #field_names: ::core::option::Option::None ),*
+        }
+      }
+    }
+
+    impl #impl_generics #builder_name #type_generics #where_clause {
+      #(
+        #[doc = ::core::concat!(
+          "Sets `",
+          ::core::stringify!(#field_names),
+          "`, which otherwise takes its default value.",
+        )]
+        #vis fn #field_names(mut self, value: #field_tys) -> Self {
+          self.#field_names = ::core::option::Option::Some(value);
+
+          self
+        }
+      )*
+
+      /// Builds the model, without initializing it.
+      #vis fn build(self) -> #name #type_generics
+      #build_where_clause
+      {
+        #name {
+          #( #build_fields ),*
+        }
+      }
 
       #init_fn_impl
     }
   }
 }
 
+fn generate_from_signals_fn_impl(
+  vis: &syn::Visibility,
+  name: &syn::Ident,
+  generics: &syn::Generics,
+  is_named: bool,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> TokenStream {
+  let krate = options.krate();
+
+  let update_model_name = options.update_name(name);
+
+  let field_names = fields
+    .iter()
+    .enumerate()
+    .map(|(i, field)| {
+      if let Some(name) = &field.name {
+        name.clone()
+      } else {
+        format_ident!("field_{i}")
+      }
+    })
+    .collect::<Vec<_>>();
+
+  let (params, update_model_fields): (Vec<_>, Vec<_>) = fields
+    .iter()
+    .zip(field_names.iter())
+    .map(|(field, field_name)| {
+      let ty = &field.ty;
+
+      // `EqSignal`s are created from plain signals, so they can be
+      // shared with code which doesn't know about them. Inputs are
+      // already signals, and are passed as they are
+      if field.is_eq && field.input.is_none() {
+        (
+          quote! {
+            #field_name: #krate::leptos_reactive::RwSignal<#ty>
+          },
+          quote! { #krate::EqSignal::from(#field_name) },
+        )
+      } else {
+        let ty = field.update_ty(&krate);
+
+        (quote! { #field_name: #ty }, quote! { #field_name })
+      }
+    })
+    .unzip();
+
+  let has_validation = has_validation(fields, options);
+
+  let validation_field = has_validation.then(|| {
+    quote! {
+      __validation: #krate::leptos_reactive::store_value(
+        ::core::option::Option::None,
+      ),
+    }
+  });
+
+  let init_validation = has_validation
+    .then(|| quote! { __update_model.view().__init_validation(); });
+
+  let init_update_model_fields = if is_named {
+    quote! {
+      { #( #field_names: #update_model_fields, )* #validation_field }
+    }
+  } else {
+    quote! { ( #( #update_model_fields ),* ) }
+  };
+
+  let (_, type_generics, _) = generics.split_for_impl();
+
+  quote! {
+    /// Creates the update model from existing signals, rather than
+    /// from a value of the model, so they stay bound to it.
+    #[allow(clippy::too_many_arguments)]
+    #vis fn from_signals(
+      #( #params ),*
+    ) -> #update_model_name #type_generics {
+      let __update_model = #update_model_name #init_update_model_fields;
+
+      #init_validation
+
+      __update_model
+    }
+  }
+}
+
+/// Bounds for building a model out of its builder, which are only
+/// checked once it's built, so fields without a `default = ...`
+/// don't need to implement `Default` unless they are left unset.
+fn builder_default_bounds(fields: &[Field]) -> Vec<syn::WherePredicate> {
+  fields
+    .iter()
+    .filter(|field| field.default.is_none())
+    .map(|Field { ty, .. }| {
+      parse_quote! { for<'__a> #ty: ::core::default::Default }
+    })
+    .collect()
+}
+
 fn generate_split_fn_impl(
   vis: &syn::Visibility,
   name: &syn::Ident,
   generics: &syn::Generics,
   is_named: bool,
   fields: &[Field],
+  options: &ModelOptions,
 ) -> TokenStream {
-  let update_model_name = format_ident!("Update{name}");
-  let view_model_name = format_ident!("View{name}");
+  let krate = options.krate();
+
+  let update_model_name = options.update_name(name);
+  let view_model_name = options.view_name(name);
 
   let field_names = fields
     .iter()
@@ -259,7 +1192,12 @@ fn generate_split_fn_impl(
     .map(
       |(
         Field {
-          is_nested_model, ..
+          is_nested_model,
+          is_eq,
+          is_stored,
+          is_skipped,
+          input,
+          ..
         },
         field_name,
       )| {
@@ -267,15 +1205,49 @@ fn generate_split_fn_impl(
         let write_name = format_ident!("__write_{field_name}");
 
         let split = if *is_nested_model {
-          quote! { let (#read_name, #write_name) = #field_name.split(); }
+          let read_pat = if *is_skipped {
+            quote! { _ }
+          } else {
+            quote! { #read_name }
+          };
+
+          quote! { let (#read_pat, #write_name) = #field_name.split(); }
         } else {
+          let write = if input.is_some() {
+            quote! { #field_name }
+          } else if *is_stored {
+            quote! { #krate::leptos_reactive::store_value(#field_name) }
+          } else if *is_eq {
+            quote! {
+              #krate::EqSignal::from(
+                #krate::leptos_reactive::create_rw_signal(#field_name),
+              )
+            }
+          } else {
+            quote! { #krate::leptos_reactive::create_rw_signal(#field_name) }
+          };
+
+          let read = if *is_skipped {
+            None
+          } else if *is_stored {
+            Some(quote! {
+              let #read_name = #krate::ReadStoredValue::from(#write_name);
+            })
+          } else if input.is_some() {
+            Some(quote! { let #read_name = #write_name; })
+          } else {
+            Some(quote! { let #read_name = #write_name.read_only(); })
+          };
+
           quote! {
-            let #write_name = ::leptos_tea::leptos_reactive::create_rw_signal(#field_name);
-            let #read_name = #write_name.read_only();
+            let #write_name = #write;
+            #read
           }
         };
 
-        (split, read_name, write_name)
+        let read = (!is_skipped).then_some((field_name, read_name));
+
+        (split, read, write_name)
       },
     )
     .collect::<Vec<_>>();
@@ -286,17 +1258,45 @@ fn generate_split_fn_impl(
   let init_update_model_fields =
     split_model_fields.iter().map(|(_, _, write)| write);
 
-  let init_view_model_fields =
-    split_model_fields.iter().map(|(_, read, _)| read);
+  let (view_field_names, init_view_model_fields): (Vec<_>, Vec<_>) =
+    split_model_fields
+      .iter()
+      .filter_map(|(_, read, _)| read.clone())
+      .unzip();
+
+  let has_validation = has_validation(fields, options);
+
+  let create_validation = has_validation.then(|| {
+    quote! {
+      let __validation = #krate::leptos_reactive::store_value(
+        ::core::option::Option::None,
+      );
+    }
+  });
+
+  let validation_field = has_validation.then(|| quote! { __validation, });
+
+  let init_validation =
+    has_validation.then(|| quote! { __view_model.__init_validation(); });
 
   let init_update_model_fields = if is_named {
-    quote! { { #( #field_names: #init_update_model_fields ),* } }
+    quote! {
+      {
+        #( #field_names: #init_update_model_fields, )*
+        #validation_field
+      }
+    }
   } else {
     quote! { ( #( #init_update_model_fields ),* ) }
   };
 
   let init_view_model_fields = if is_named {
-    quote! { { #( #field_names: #init_view_model_fields ),* } }
+    quote! {
+      {
+        #( #view_field_names: #init_view_model_fields, )*
+        #validation_field
+      }
+    }
   } else {
     quote! { ( #( #init_view_model_fields ),* ) }
   };
@@ -311,9 +1311,13 @@ fn generate_split_fn_impl(
 
       #( #split_model_fields_exprs )*
 
+      #create_validation
+
       let __view_model = #view_model_name #init_view_model_fields;
       let __update_model = #update_model_name #init_update_model_fields;
 
+      #init_validation
+
       (__view_model, __update_model)
     }
   }
@@ -323,57 +1327,304 @@ fn generate_init_fn_impl(
   vis: &syn::Visibility,
   name: &syn::Ident,
   generics: &syn::Generics,
-) -> TokenStream {
-  let update_model_name = format_ident!("Update{name}");
-  let view_model_name = format_ident!("View{name}");
+  is_named: bool,
+  fields: &[Field],
+  options: &ModelOptions,
+) -> (TokenStream, TokenStream, TokenStream) {
+  let krate = options.krate();
+
+  let update_model_name = options.update_name(name);
+  let view_model_name = options.view_name(name);
 
   let (_, type_generics, _) = generics.split_for_impl();
 
-  quote! {
+  let (load, save) = if let Some(Persist { key, storage }) = &options.persist {
+    let storage = storage.as_ref().map_or_else(
+      || quote! { #krate::persist::default_storage() },
+      |storage| quote! { #storage },
+    );
+
+    let version = options
+      .version
+      .as_ref()
+      .map(|version| quote! { .version(#version) });
+
+    let migrate = options
+      .migrate
+      .as_ref()
+      .map(|migrate| quote! { .migrate(#migrate) });
+
+    // Restored into the update model rather than loaded in place of
+    // the model, so skipped fields keep the value they were
+    // initialized with
+    let load = quote! {
+      let __persister =
+        #krate::persist::Persister::new(#storage, #key)
+          #version
+          #migrate;
+
+      if let ::core::option::Option::Some(__model) = __persister.load() {
+        __update_model.restore(__model);
+      }
+    };
+
+    let save = quote! {
+      __persister.save_later(move || __view_model.snapshot());
+    };
+
+    (load, save)
+  } else {
+    (quote! {}, quote! {})
+  };
+
+  let mut msg_bounds = Vec::new();
+
+  let init = match &options.ssr {
+    Some(Ssr { msg_log: false }) => quote! {
+      #krate::ssr::init(
+        __update_fn,
+        options,
+        #krate::ssr::SnapshotTransfer::new(
+          move || __view_model.snapshot(),
+          move |model| __update_model.restore(model),
+        ),
+      )
+    },
+    Some(Ssr { msg_log: true }) => {
+      msg_bounds.push(quote! { __Msg: #krate::ssr::Transferable });
+
+      quote! {
+        #krate::ssr::init(
+          __update_fn,
+          options,
+          #krate::ssr::MsgLogTransfer::default(),
+        )
+      }
+    }
+    None => quote! { #krate::init(__update_fn, options) },
+  };
+
+  // Inputs with `on_change` dispatch a message whenever the signal
+  // changes, but not for the value it starts with
+  let watch_inputs = fields
+    .iter()
+    .enumerate()
+    .filter_map(|(i, field)| {
+      let Some(Input {
+        on_change: Some(on_change),
+        msg: Some(msg),
+      }) = &field.input
+      else {
+        return None;
+      };
+
+      let member = if is_named {
+        syn::Member::Named(field.name.clone().unwrap())
+      } else {
+        syn::Member::Unnamed(i.into())
+      };
+
+      msg_bounds.push(quote! { __Msg: #krate::SameAs<#msg> });
+
+      Some(quote! {
+        {
+          let __input = __update_model.#member;
+          let __on_change = #on_change;
+
+          #krate::leptos_reactive::create_effect(
+            move |__prev: ::core::option::Option<()>| {
+              let __value =
+                #krate::leptos_reactive::SignalGet::get(&__input);
+
+              if __prev.is_some() {
+                __msg_dispatcher.dispatch(
+                  <__Msg as #krate::SameAs<#msg>>::from_same(
+                    __on_change(__value),
+                  ),
+                );
+              }
+            },
+          );
+        }
+      })
+    })
+    .collect::<Vec<_>>();
+
+  let where_clause =
+    (!msg_bounds.is_empty()).then(|| quote! { where #( #msg_bounds ),* });
+
+  // `init`, `init_with` and `init_with_handle` for the model, its
+  // update model and its builder, which differ only in how the update
+  // model is created
+  let init_fns = |init_with_handle: TokenStream,
+                  bounds: &[syn::WherePredicate]| {
+    let where_clause = (!msg_bounds.is_empty() || !bounds.is_empty())
+      .then(|| quote! { where #( #msg_bounds, )* #( #bounds ),* });
+
+    quote! {
+      #[track_caller]
+      #vis fn init<__Msg: ::core::default::Default + 'static>(
+        self,
+        update_fn: impl ::core::ops::Fn(
+          #update_model_name #type_generics,
+          __Msg,
+          #krate::Cmd<__Msg>,
+        ) + 'static
+      ) -> (#view_model_name #type_generics, #krate::MsgDispatcher<__Msg>)
+      #where_clause
+      {
+        self.init_with(update_fn, ::core::default::Default::default())
+      }
+
+      #[track_caller]
+      #vis fn init_with<__Msg: ::core::default::Default + 'static>(
+        self,
+        update_fn: impl ::core::ops::Fn(
+          #update_model_name #type_generics,
+          __Msg,
+          #krate::Cmd<__Msg>,
+        ) + 'static,
+        options: #krate::Options<__Msg>,
+      ) -> (#view_model_name #type_generics, #krate::MsgDispatcher<__Msg>)
+      #where_clause
+      {
+        let (__view_model, __msg_dispatcher, _) =
+          self.init_with_handle(update_fn, options);
+
+        (__view_model, __msg_dispatcher)
+      }
+
+      #[track_caller]
+      #vis fn init_with_handle<__Msg: ::core::default::Default + 'static>(
+        self,
+        update_fn: impl ::core::ops::Fn(
+          #update_model_name #type_generics,
+          __Msg,
+          #krate::Cmd<__Msg>,
+        ) + 'static,
+        options: #krate::Options<__Msg>,
+      ) -> (
+        #view_model_name #type_generics,
+        #krate::MsgDispatcher<__Msg>,
+        #krate::ProgramHandle,
+      )
+      #where_clause
+      {
+        #init_with_handle
+      }
+    }
+  };
+
+  // Persistence is wired up here, so update models created with
+  // `from_signals()` are persisted too
+  let update_model_init_fns = init_fns(
+    quote! {
+      let __update_model = self;
+      let __view_model = __update_model.view();
+
+      #load
+
+      let __update_fn = move |msg: __Msg, cmd: #krate::Cmd<__Msg>| {
+        update_fn(__update_model, msg, cmd);
+
+        #save
+      };
+
+      let (__msg_dispatcher, __program_handle) = #init;
+
+      #( #watch_inputs )*
+
+      (__view_model, __msg_dispatcher, __program_handle)
+    },
+    &[],
+  );
+
+  let model_init_fns = init_fns(
+    quote! {
+      let (_, __update_model) = self.split();
+
+      __update_model.init_with_handle(update_fn, options)
+    },
+    &[],
+  );
+
+  let builder_init_fns = init_fns(
+    quote! { self.build().init_with_handle(update_fn, options) },
+    &builder_default_bounds(fields),
+  );
+
+  let model_fns = quote! {
+    #model_init_fns
+
     #[track_caller]
-    #vis fn init<Msg: ::core::default::Default + 'static>(
+    #vis fn provide<__Msg: ::core::default::Default + 'static>(
       self,
       update_fn: impl ::core::ops::Fn(
         #update_model_name #type_generics,
-        Msg,
-        ::leptos_tea::Cmd<Msg>,
+        __Msg,
+        #krate::Cmd<__Msg>,
       ) + 'static
-    ) -> (#view_model_name #type_generics, ::leptos_tea::MsgDispatcher<Msg>) {
-      let __update_fn = update_fn;
-
-      let (__tx, mut __rx)
-        = ::leptos_tea::futures::channel::mpsc::unbounded();
-
-      __tx.unbounded_send(Msg::default()).unwrap();
-
-      let __tx_store = ::leptos_tea::leptos_reactive::store_value(__tx);
-
-      let (__view_model, __update_model) = self.split();
-
-      ::leptos_tea::leptos_reactive::try_spawn_local_with_current_owner(
-        async move {
-          while let Some(msg)
-            = ::leptos_tea::futures::StreamExt::next(&mut __rx).await
-          {
-            let __cmd_dispatcher = ::leptos_tea::Cmd::new(
-              ::leptos_tea::leptos_reactive::Owner::current()
-                .expect(
-                  "`Model::init()` must be called  within \
-                  the context of an `Owner`\n\
-                  \n\
-                  try calling `Model::init()` at the top level of \
-                  the component definition"
-                ),
-              __tx_store,
-            );
-
-            __update_fn(__update_model, msg, __cmd_dispatcher);
-          }
-        },
-        || {},
-      );
+    ) -> (#view_model_name #type_generics, #krate::MsgDispatcher<__Msg>)
+    #where_clause
+    {
+      let (__view_model, __msg_dispatcher) = self.init(update_fn);
 
-      (__view_model, ::leptos_tea::MsgDispatcher::new(__tx_store))
+      #krate::leptos_reactive::provide_context(__view_model);
+      // Keyed by the model as well, so models sharing a `Msg` type
+      // don't overwrite each other's dispatcher
+      #krate::leptos_reactive::provide_context((
+        ::core::marker::PhantomData::<Self>,
+        __msg_dispatcher,
+      ));
+
+      (__view_model, __msg_dispatcher)
     }
-  }
+
+    #[track_caller]
+    #vis fn init_global<__Msg: ::core::default::Default + 'static>(
+      self,
+      update_fn: impl ::core::ops::Fn(
+        #update_model_name #type_generics,
+        __Msg,
+        #krate::Cmd<__Msg>,
+      ) + 'static
+    )
+    #where_clause
+    {
+      #krate::store::register::<Self, __Msg>(move || self.init(update_fn));
+    }
+
+    #[track_caller]
+    #vis fn use_view() -> #view_model_name #type_generics {
+      #krate::leptos_reactive::use_context().unwrap_or_else(|| {
+        ::core::panic!(
+          "no `{}` found in context\n\ntry calling `{}::provide()` in \
+           a parent component",
+          ::core::stringify!(#view_model_name),
+          ::core::stringify!(#name),
+        )
+      })
+    }
+
+    #[track_caller]
+    #vis fn use_dispatcher<__Msg: 'static>() -> #krate::MsgDispatcher<__Msg> {
+      #krate::leptos_reactive::use_context::<(
+        ::core::marker::PhantomData<Self>,
+        #krate::MsgDispatcher<__Msg>,
+      )>()
+      .map(|(_, msg_dispatcher)| msg_dispatcher)
+      .unwrap_or_else(|| {
+        ::core::panic!(
+          "no `MsgDispatcher<{}>` for `{}` found in context\n\ntry \
+           calling `{}::provide()` in a parent component, and make sure \
+           the message type matches",
+          ::core::any::type_name::<__Msg>(),
+          ::core::stringify!(#name),
+          ::core::stringify!(#name),
+        )
+      })
+    }
+  };
+
+  (model_fns, update_model_init_fns, builder_init_fns)
 }
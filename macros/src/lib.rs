@@ -195,6 +195,7 @@ extern crate proc_macro_error;
 
 mod codegen;
 mod model;
+mod msg;
 
 use proc_macro_error::proc_macro_error;
 
@@ -207,3 +208,13 @@ pub fn model(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
 
   codegen::codegen(model).into()
 }
+
+#[proc_macro_derive(Msg, attributes(msg))]
+#[proc_macro_error]
+pub fn msg(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+  let ast = syn::parse_macro_input!(stream as syn::DeriveInput);
+
+  let msg = msg::msg(ast);
+
+  msg::codegen(msg).into()
+}
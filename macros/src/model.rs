@@ -1,19 +1,22 @@
+use quote::format_ident;
+
 pub struct Model {
   pub vis: syn::Visibility,
   pub name: syn::Ident,
   pub generics: syn::Generics,
   pub is_named: bool,
   pub fields: Vec<Field>,
+  pub options: ModelOptions,
 }
 
 impl From<syn::DeriveInput> for Model {
   fn from(
     syn::DeriveInput {
+      attrs,
       ident: name,
       vis,
       generics,
       data,
-      ..
     }: syn::DeriveInput,
   ) -> Self {
     match data {
@@ -22,12 +25,90 @@ impl From<syn::DeriveInput> for Model {
           abort!(name, "unit structs are not supported");
         }
 
+        let options = ModelOptions::from_attrs(&attrs);
+
+        let is_named = matches!(fields, syn::Fields::Named(_));
+
+        let fields = fields
+          .into_iter()
+          .map(Field::from)
+          .map(|mut field| {
+            field.is_eq |= options.eq
+              && !field.is_nested_model
+              && !field.is_stored
+              && field.input.is_none();
+
+            field
+          })
+          .collect::<Vec<_>>();
+
+        if !is_named {
+          if let Some(validate) = options
+            .validate
+            .as_ref()
+            .or_else(|| fields.iter().find_map(|field| field.validate.as_ref()))
+          {
+            abort!(
+              validate,
+              "`validate` is only supported on structs with named fields"
+            );
+          }
+        }
+
+        if options.form {
+          if !is_named {
+            abort!(
+              name,
+              "`form` is only supported on structs with named fields"
+            );
+          }
+
+          // The field message enum isn't generic, so it couldn't hold
+          // fields of generic types
+          if !generics.params.is_empty() {
+            abort!(generics, "`form` is not supported on generic models");
+          }
+        }
+
+        if options.builder {
+          if !is_named {
+            abort!(
+              name,
+              "`builder` is only supported on structs with named fields"
+            );
+          }
+
+          // Setters are named after the fields, and share the builder
+          // with these methods
+          if let Some(name) = fields
+            .iter()
+            .filter_map(|field| field.name.as_ref())
+            .find(|name| {
+              ["build", "init", "init_with", "init_with_handle"]
+                .iter()
+                .any(|reserved| syn::ext::IdentExt::unraw(*name) == reserved)
+            })
+          {
+            abort!(
+              name,
+              "`{}` can't be used as a field name with `#[model(builder)]`, \
+               since the builder already has a method with that name",
+              name
+            );
+          }
+        } else if let Some(default) =
+          fields.iter().find_map(|field| field.default.as_ref())
+        {
+          abort!(default, "`default` requires `#[model(builder)]`");
+        }
+
         Model {
           vis,
           name,
           generics,
-          is_named: matches!(fields, syn::Fields::Named(_)),
-          fields: fields.into_iter().map(Field::from).collect(),
+          is_named,
+          fields,
+          options,
         }
       }
       syn::Data::Enum(e) => abort!(e.enum_token, "enums are not supported"),
@@ -38,11 +119,252 @@ impl From<syn::DeriveInput> for Model {
   }
 }
 
+/// Options set with `#[model(...)]` on the struct itself.
+#[derive(Default)]
+pub struct ModelOptions {
+  pub persist: Option<Persist>,
+  pub version: Option<syn::LitInt>,
+  pub migrate: Option<syn::Expr>,
+  pub ssr: Option<Ssr>,
+  pub eq: bool,
+  /// Derives for the generated view and update models.
+  pub derives: Vec<syn::Path>,
+  /// Attributes for the generated view and update models.
+  pub attrs: Vec<syn::Meta>,
+  pub update: Option<syn::Ident>,
+  pub view: Option<syn::Ident>,
+  /// Path `leptos_tea` is reachable at, for crates re-exporting it.
+  pub krate: Option<syn::Path>,
+  /// Generates a builder for the model.
+  pub builder: bool,
+  /// Validates the view model as a whole.
+  pub validate: Option<syn::Expr>,
+  /// Generates a message enum and bindings for form inputs.
+  pub form: bool,
+}
+
+impl ModelOptions {
+  fn from_attrs(attrs: &[syn::Attribute]) -> Self {
+    let mut options = Self::default();
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("model")) {
+      let res = attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("persist") {
+          options.persist = Some(Persist::parse(meta)?);
+        } else if meta.path.is_ident("version") {
+          let version = meta.value()?.parse::<syn::LitInt>()?;
+
+          version.base10_parse::<u32>()?;
+
+          options.version = Some(version);
+        } else if meta.path.is_ident("migrate") {
+          options.migrate = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("eq") {
+          options.eq = true;
+        } else if meta.path.is_ident("builder") {
+          options.builder = true;
+        } else if meta.path.is_ident("form") {
+          options.form = true;
+        } else if meta.path.is_ident("validate") {
+          options.validate = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("derive") {
+          meta.parse_nested_meta(|meta| {
+            options.derives.push(meta.path);
+
+            Ok(())
+          })?;
+        } else if meta.path.is_ident("attr") {
+          let content;
+          syn::parenthesized!(content in meta.input);
+
+          options.attrs.push(content.parse()?);
+        } else if meta.path.is_ident("ssr") {
+          options.ssr = Some(Ssr::parse(meta)?);
+        } else if meta.path.is_ident("crate") {
+          let value = meta.value()?;
+
+          options.krate = Some(if value.peek(syn::LitStr) {
+            value.parse::<syn::LitStr>()?.parse()?
+          } else {
+            value.parse()?
+          });
+        } else if meta.path.is_ident("update") {
+          options.update = Some(meta.value()?.parse::<syn::LitStr>()?.parse()?);
+        } else if meta.path.is_ident("view") {
+          options.view = Some(meta.value()?.parse::<syn::LitStr>()?.parse()?);
+        } else {
+          return Err(meta.error("unknown model option"));
+        }
+
+        Ok(())
+      });
+
+      if let Err(err) = res {
+        abort!(err.span(), "{}", err);
+      }
+    }
+
+    if options.persist.is_none() {
+      if let Some(version) = &options.version {
+        abort!(version, "`version` requires `persist(...)`");
+      }
+
+      if let Some(migrate) = &options.migrate {
+        abort!(migrate, "`migrate` requires `persist(...)`");
+      }
+    }
+
+    options
+  }
+
+  /// Path to the `leptos_tea` crate, `::leptos_tea` by default.
+  pub fn krate(&self) -> syn::Path {
+    self
+      .krate
+      .clone()
+      .unwrap_or_else(|| syn::parse_quote! { ::leptos_tea })
+  }
+
+  /// Name of the generated update model, `Update{name}` by default.
+  pub fn update_name(&self, name: &syn::Ident) -> syn::Ident {
+    self
+      .update
+      .clone()
+      .unwrap_or_else(|| format_ident!("Update{name}"))
+  }
+
+  /// Name of the generated view model, `View{name}` by default.
+  pub fn view_name(&self, name: &syn::Ident) -> syn::Ident {
+    self
+      .view
+      .clone()
+      .unwrap_or_else(|| format_ident!("View{name}"))
+  }
+}
+
+/// `#[model(persist(key = "...", storage = ...))]`
+pub struct Persist {
+  pub key: syn::LitStr,
+  pub storage: Option<syn::Expr>,
+}
+
+impl Persist {
+  fn parse(meta: syn::meta::ParseNestedMeta) -> syn::Result<Self> {
+    let mut key = None;
+    let mut storage = None;
+
+    meta.parse_nested_meta(|meta| {
+      if meta.path.is_ident("key") {
+        key = Some(meta.value()?.parse()?);
+      } else if meta.path.is_ident("storage") {
+        storage = Some(meta.value()?.parse()?);
+      } else {
+        return Err(meta.error("unknown persist option"));
+      }
+
+      Ok(())
+    })?;
+
+    let Some(key) = key else {
+      return Err(meta.error("missing `key = \"...\"`"));
+    };
+
+    Ok(Self { key, storage })
+  }
+}
+
+/// `#[model(ssr)]` or `#[model(ssr(msg_log))]`
+pub struct Ssr {
+  pub msg_log: bool,
+}
+
+impl Ssr {
+  fn parse(meta: syn::meta::ParseNestedMeta) -> syn::Result<Self> {
+    let mut msg_log = false;
+
+    if meta.input.peek(syn::token::Paren) {
+      meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("msg_log") {
+          msg_log = true;
+        } else {
+          return Err(meta.error("unknown ssr option"));
+        }
+
+        Ok(())
+      })?;
+    }
+
+    Ok(Self { msg_log })
+  }
+}
+
+/// `#[model(input)]` or `#[model(input(on_change = expr, msg = Type))]`
+pub struct Input {
+  pub on_change: Option<syn::Expr>,
+  /// The message type `on_change` returns.
+  pub msg: Option<syn::Type>,
+}
+
+impl Input {
+  fn parse(meta: syn::meta::ParseNestedMeta) -> syn::Result<Self> {
+    let mut on_change = None;
+    let mut msg = None;
+
+    if meta.input.peek(syn::token::Paren) {
+      meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("on_change") {
+          on_change = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("msg") {
+          msg = Some(meta.value()?.parse()?);
+        } else {
+          return Err(meta.error("unknown input option"));
+        }
+
+        Ok(())
+      })?;
+    }
+
+    // `Msg::Variant` returns `Msg`
+    if let (Some(syn::Expr::Path(path)), None) = (&on_change, &msg) {
+      let mut path = path.path.clone();
+
+      if path.segments.len() > 1 {
+        path.segments.pop();
+        path.segments.pop_punct();
+
+        msg = Some(syn::Type::Path(syn::TypePath { qself: None, path }));
+      }
+    }
+
+    if let (Some(on_change), None) = (&on_change, &msg) {
+      return Err(syn::Error::new_spanned(
+        on_change,
+        "can't infer the message type, try adding `msg = Type`",
+      ));
+    }
+
+    Ok(Self { on_change, msg })
+  }
+}
+
 pub struct Field {
   pub vis: syn::Visibility,
   pub name: Option<syn::Ident>,
   pub ty: syn::Type,
   pub is_nested_model: bool,
+  pub is_eq: bool,
+  /// Held in a `StoredValue` rather than a signal.
+  pub is_stored: bool,
+  /// Left out of the view model.
+  pub is_skipped: bool,
+  /// External signal passed in when initializing the model.
+  pub input: Option<Input>,
+  pub update_vis: Option<syn::Visibility>,
+  pub view_vis: Option<syn::Visibility>,
+  /// Value the builder uses when the field isn't set.
+  pub default: Option<syn::Expr>,
+  /// Validates the value of the field.
+  pub validate: Option<syn::Expr>,
 }
 
 impl From<syn::Field> for Field {
@@ -55,11 +377,93 @@ impl From<syn::Field> for Field {
       ..
     }: syn::Field,
   ) -> Self {
-    Self {
+    let mut field = Self {
       vis,
       name,
       ty,
       is_nested_model: is_nested_model(&attrs),
+      is_eq: false,
+      is_stored: false,
+      is_skipped: false,
+      input: None,
+      update_vis: None,
+      view_vis: None,
+      default: None,
+      validate: None,
+    };
+
+    field.parse_options(&attrs);
+
+    field
+  }
+}
+
+impl Field {
+  /// Parses `#[model(...)]` on the field, other than the bare
+  /// `#[model]` marking nested models.
+  fn parse_options(&mut self, attrs: &[syn::Attribute]) {
+    for attr in attrs.iter().filter(|attr| {
+      attr.path().is_ident("model") && matches!(attr.meta, syn::Meta::List(_))
+    }) {
+      let res = attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("eq") {
+          self.is_eq = true;
+        } else if meta.path.is_ident("store") {
+          self.is_stored = true;
+        } else if meta.path.is_ident("skip") {
+          self.is_skipped = true;
+        } else if meta.path.is_ident("input") {
+          self.input = Some(Input::parse(meta)?);
+        } else if meta.path.is_ident("update_vis") {
+          self.update_vis = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("view_vis") {
+          self.view_vis = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("default") {
+          self.default = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("validate") {
+          self.validate = Some(meta.value()?.parse()?);
+        } else {
+          return Err(meta.error("unknown field option"));
+        }
+
+        Ok(())
+      });
+
+      if let Err(err) = res {
+        abort!(err.span(), "{}", err);
+      }
+    }
+
+    if self.is_nested_model {
+      if self.is_eq {
+        abort!(self.ty, "`eq` can't be used on nested models");
+      }
+
+      if self.is_stored {
+        abort!(self.ty, "`store` can't be used on nested models");
+      }
+    }
+
+    if self.is_eq && self.is_stored {
+      abort!(self.ty, "`eq` and `store` can't be used together");
+    }
+
+    if self.input.is_some()
+      && (self.is_nested_model || self.is_eq || self.is_stored)
+    {
+      abort!(
+        self.ty,
+        "`input` can't be used with `#[model]`, `eq` or `store`"
+      );
+    }
+
+    if let Some(validate) = &self.validate {
+      if self.is_nested_model || self.is_stored || self.is_skipped {
+        abort!(
+          validate,
+          "`validate` can't be used with `#[model]`, `store` or `skip`"
+        );
+      }
     }
   }
 }
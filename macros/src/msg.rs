@@ -0,0 +1,329 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+pub struct Msg {
+  vis: syn::Visibility,
+  name: syn::Ident,
+  variants: Vec<Variant>,
+  /// Path `leptos_tea` is reachable at, for crates re-exporting it.
+  krate: Option<syn::Path>,
+}
+
+impl From<syn::DeriveInput> for Msg {
+  fn from(
+    syn::DeriveInput {
+      attrs,
+      ident: name,
+      vis,
+      generics,
+      data,
+    }: syn::DeriveInput,
+  ) -> Self {
+    let syn::Data::Enum(syn::DataEnum { variants, .. }) = data else {
+      abort!(name, "only enums are supported");
+    };
+
+    if !generics.params.is_empty() {
+      abort!(generics, "generic message enums are not supported");
+    }
+
+    let mut krate = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("msg")) {
+      let res = attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("crate") {
+          let value = meta.value()?;
+
+          krate = Some(if value.peek(syn::LitStr) {
+            value.parse::<syn::LitStr>()?.parse()?
+          } else {
+            value.parse()?
+          });
+        } else {
+          return Err(meta.error("unknown msg option"));
+        }
+
+        Ok(())
+      });
+
+      if let Err(err) = res {
+        abort!(err.span(), "{}", err);
+      }
+    }
+
+    let variants = variants.into_iter().map(Variant::from).collect::<Vec<_>>();
+
+    if let Some(variant) =
+      variants.iter().filter(|variant| variant.is_init).nth(1)
+    {
+      abort!(variant.name, "only one variant can be `init`");
+    }
+
+    Self {
+      vis,
+      name,
+      variants,
+      krate,
+    }
+  }
+}
+
+struct Variant {
+  name: syn::Ident,
+  fields: syn::Fields,
+  /// Doc comments, forwarded to the dispatcher method.
+  docs: Vec<syn::Attribute>,
+  /// Sent to the update function when the model is initialized.
+  is_init: bool,
+}
+
+impl From<syn::Variant> for Variant {
+  fn from(
+    syn::Variant {
+      attrs,
+      ident: name,
+      fields,
+      ..
+    }: syn::Variant,
+  ) -> Self {
+    let mut is_init = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("msg")) {
+      let res = attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("init") {
+          is_init = true;
+        } else {
+          return Err(meta.error("unknown variant option"));
+        }
+
+        Ok(())
+      });
+
+      if let Err(err) = res {
+        abort!(err.span(), "{}", err);
+      }
+    }
+
+    if is_init && !matches!(fields, syn::Fields::Unit) {
+      abort!(name, "`init` can only be used on unit variants");
+    }
+
+    let docs = attrs
+      .into_iter()
+      .filter(|attr| attr.path().is_ident("doc"))
+      .collect();
+
+    Self {
+      name,
+      fields,
+      docs,
+      is_init,
+    }
+  }
+}
+
+/// Derives helpers for dispatching and naming messages.
+pub fn msg(ast: syn::DeriveInput) -> Msg {
+  Msg::from(ast)
+}
+
+pub fn codegen(
+  Msg {
+    vis,
+    name,
+    variants,
+    krate,
+  }: Msg,
+) -> TokenStream {
+  let krate = krate.unwrap_or_else(|| syn::parse_quote! { ::leptos_tea });
+
+  let trait_name = format_ident!("Dispatch{name}");
+
+  let (trait_fns, impl_fns): (Vec<_>, Vec<_>) = variants
+    .iter()
+    .map(|variant| generate_dispatch_fn(&krate, &name, variant))
+    .unzip();
+
+  let variant_names = variants.iter().map(
+    |Variant {
+       name: variant,
+       fields,
+       ..
+     }| {
+      let pat = match fields {
+        syn::Fields::Named(_) => quote! { { .. } },
+        syn::Fields::Unnamed(_) => quote! { (..) },
+        syn::Fields::Unit => quote! {},
+      };
+
+      quote! { Self::#variant #pat => ::core::stringify!(#variant) }
+    },
+  );
+
+  // Uninhabited enums can't be matched on by reference
+  let variant_name_body = if variants.is_empty() {
+    quote! { match *self {} }
+  } else {
+    quote! {
+      match self {
+        #( #variant_names ),*
+      }
+    }
+  };
+
+  let default_impl =
+    variants
+      .iter()
+      .find(|variant| variant.is_init)
+      .map(|variant| {
+        let variant = &variant.name;
+
+        quote! {
+          impl ::core::default::Default for #name {
+            fn default() -> Self {
+              Self::#variant
+            }
+          }
+        }
+      });
+
+  quote! {
+    impl #name {
+      /// Returns the name of the variant, such as for tracing spans,
+      /// without requiring `Debug`.
+      #vis fn variant_name(&self) -> &'static str {
+        #variant_name_body
+      }
+    }
+
+    #default_impl
+
+    /// Dispatches each variant of
+    #[doc = ::core::concat!("[`", ::core::stringify!(#name), "`]")]
+    /// with a method of its own.
+    #vis trait #trait_name {
+      #( #trait_fns )*
+    }
+
+    impl #trait_name for #krate::MsgDispatcher<#name> {
+      #( #impl_fns )*
+    }
+  }
+}
+
+/// Generates the trait method dispatching `variant`, along with its
+/// implementation.
+fn generate_dispatch_fn(
+  krate: &syn::Path,
+  name: &syn::Ident,
+  Variant {
+    name: variant,
+    fields,
+    docs,
+    ..
+  }: &Variant,
+) -> (TokenStream, TokenStream) {
+  let fn_name = to_ident(&to_snake_case(&variant.to_string()));
+
+  let (params, msg) = match fields {
+    syn::Fields::Named(fields) => {
+      let (params, names): (Vec<_>, Vec<_>) = fields
+        .named
+        .iter()
+        .map(|field| {
+          let name = field.ident.as_ref().unwrap();
+          let ty = &field.ty;
+
+          (quote! { #name: #ty }, name)
+        })
+        .unzip();
+
+      (params, quote! { #name::#variant { #( #names ),* } })
+    }
+    syn::Fields::Unnamed(fields) => {
+      let (params, names): (Vec<_>, Vec<_>) = fields
+        .unnamed
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+          let name = if fields.unnamed.len() == 1 {
+            format_ident!("value")
+          } else {
+            format_ident!("value_{i}")
+          };
+          let ty = &field.ty;
+
+          (quote! { #name: #ty }, name)
+        })
+        .unzip();
+
+      (params, quote! { #name::#variant( #( #names ),* ) })
+    }
+    syn::Fields::Unit => (vec![], quote! { #name::#variant }),
+  };
+
+  let docs = if docs.is_empty() {
+    quote! {
+      #[doc = ::core::concat!(
+        "Dispatches [`",
+        ::core::stringify!(#name),
+        "::",
+        ::core::stringify!(#variant),
+        "`].",
+      )]
+    }
+  } else {
+    quote! { #( #docs )* }
+  };
+
+  let trait_fn = quote! {
+    #docs
+    fn #fn_name(&self #( , #params )*);
+  };
+
+  // Called by path, since a variant such as `Dispatch` would otherwise
+  // call itself
+  let impl_fn = quote! {
+    fn #fn_name(&self #( , #params )*) {
+      #krate::MsgDispatcher::dispatch(*self, #msg);
+    }
+  };
+
+  (trait_fn, impl_fn)
+}
+
+/// `SetFilter` -> `set_filter`, `HTTPError` -> `http_error`
+fn to_snake_case(name: &str) -> String {
+  let chars = name.chars().collect::<Vec<_>>();
+
+  let mut snake = String::with_capacity(name.len() + 4);
+
+  for (i, &c) in chars.iter().enumerate() {
+    if c.is_uppercase() && i > 0 {
+      let prev = chars[i - 1];
+      let next = chars.get(i + 1);
+
+      if prev.is_lowercase()
+        || prev.is_numeric()
+        || (prev.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+      {
+        snake.push('_');
+      }
+    }
+
+    snake.extend(c.to_lowercase());
+  }
+
+  snake
+}
+
+/// Keywords, such as `type` from `Type`, need to be raw identifiers,
+/// other than those which can't be, such as `self` from `Self_`.
+fn to_ident(name: &str) -> syn::Ident {
+  match name {
+    "self" | "super" | "crate" => format_ident!("{name}_"),
+    _ => syn::parse_str::<syn::Ident>(name).unwrap_or_else(|_| {
+      syn::Ident::new_raw(name, proc_macro2::Span::call_site())
+    }),
+  }
+}